@@ -0,0 +1,100 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Currency amounts with checked arithmetic.
+
+use exonum::encoding::{
+    serialize::{json::ExonumJson, WriteBufferWrapper}, CheckedOffset, Field, Offset,
+    Result as EncodingResult,
+};
+use serde_json::Value;
+
+use std::error::Error as StdError;
+
+use transactions::Error;
+
+/// Amount of the currency. Arithmetic on amounts never wraps or panics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Amount(pub u64);
+
+impl Amount {
+    /// Adds two amounts, failing with `BalanceOverflow` if the sum doesn't fit.
+    pub fn checked_add(self, other: Amount) -> Result<Amount, Error> {
+        self.0
+            .checked_add(other.0)
+            .map(Amount)
+            .ok_or(Error::BalanceOverflow)
+    }
+
+    /// Subtracts an amount, failing with `InsufficientCurrencyAmount` if it is greater.
+    pub fn checked_sub(self, other: Amount) -> Result<Amount, Error> {
+        self.0
+            .checked_sub(other.0)
+            .map(Amount)
+            .ok_or(Error::InsufficientCurrencyAmount)
+    }
+}
+
+impl From<u64> for Amount {
+    fn from(value: u64) -> Self {
+        Amount(value)
+    }
+}
+
+impl From<Amount> for u64 {
+    fn from(value: Amount) -> Self {
+        value.0
+    }
+}
+
+// Amounts are stored and serialized exactly like `u64`, so they can be used as fields
+// of encoding structures and transactions.
+#[allow(unsafe_code)]
+impl<'a> Field<'a> for Amount {
+    fn field_size() -> Offset {
+        <u64 as Field>::field_size()
+    }
+
+    unsafe fn read(buffer: &'a [u8], from: Offset, to: Offset) -> Self {
+        Amount(<u64 as Field>::read(buffer, from, to))
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>, from: Offset, to: Offset) {
+        self.0.write(buffer, from, to)
+    }
+
+    fn check(
+        buffer: &'a [u8],
+        from: CheckedOffset,
+        to: CheckedOffset,
+        latest_segment: CheckedOffset,
+    ) -> EncodingResult {
+        <u64 as Field>::check(buffer, from, to, latest_segment)
+    }
+}
+
+impl ExonumJson for Amount {
+    fn deserialize_field<B: WriteBufferWrapper>(
+        value: &Value,
+        buffer: &mut B,
+        from: Offset,
+        to: Offset,
+    ) -> Result<(), Box<dyn StdError>> {
+        <u64 as ExonumJson>::deserialize_field(value, buffer, from, to)
+    }
+
+    fn serialize_field(&self) -> Result<Value, Box<dyn StdError + Send + Sync>> {
+        self.0.serialize_field()
+    }
+}
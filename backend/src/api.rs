@@ -14,15 +14,38 @@
 
 //! Cryptocurrency API.
 
+use actix_web::{http::Method, FromRequest, HttpResponse, Query};
 use exonum::{
-    api::{self, ServiceApiBuilder, ServiceApiState},
-    blockchain::{self, BlockProof, Transaction, TransactionSet}, crypto::{Hash, PublicKey},
-    helpers::Height, node::TransactionSend, storage::{ListProof, MapProof},
+    api::{
+        self, backends::actix::{FutureResponse, HttpRequest, RequestHandler}, ServiceApiBuilder,
+        ServiceApiState,
+    },
+    blockchain::{self, BlockProof, Transaction, TransactionSet}, crypto::{self, Hash, PublicKey},
+    helpers::Height, messages::Message, node::TransactionSend,
+    storage::{ListProof, MapProof, Snapshot},
 };
 
-use transactions::WalletTransactions;
+use chrono::{DateTime, Utc};
+use exonum_time::schema::TimeSchema;
+use futures::future;
+
+use std::{sync::Arc, time::Duration};
+
+use schema::{
+    asset_balance_key, balance_record_key, hour_bucket, normalize_name, Asset, AssetBalance,
+    BalanceRecord, TimeIndexEntry, Invoice, Limits, NameRecord, Redemption, Stamp,
+    StandingOrderPayment, Voucher,
+};
+use transactions::{
+    Error, ErrorDescription, ExpirePreparation, IndexTimestamps, TransferWithReference,
+    WalletTransactions,
+};
+use events::EventRecord;
+use export::{self, ExportFormat, Movement};
+use notifier::BlockNotifier;
+use token::PostageToken;
 use wallet::Wallet;
-use {CurrencySchema, POST_SERVICE_ID};
+use {CurrencySchema, MAX_INDEX_BATCH, POST_SERVICE_ID, PREPARATION_TTL};
 
 /// The structure describes the query parameters for the `get_wallet` endpoint.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -31,6 +54,167 @@ pub struct WalletQuery {
     pub pub_key: PublicKey,
 }
 
+/// The structure describes the query parameters for the `transfers_by_reference` endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReferenceQuery {
+    /// Reference of the queried transfers.
+    pub reference: String,
+}
+
+/// The structure describes the query parameters for the `lookup_wallet` endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NameQuery {
+    /// Registered name of the wallet.
+    pub name: String,
+}
+
+/// The structure describes the query parameters for paginated listing endpoints.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct PageQuery {
+    /// Public key to start listing from, as returned in `Page::next`.
+    pub from: Option<PublicKey>,
+    /// Maximal number of items on the page.
+    pub count: Option<usize>,
+}
+
+/// Default number of items on a page.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+/// Maximal number of items on a page.
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Page of items listed by public key.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// Items on the page.
+    pub items: Vec<T>,
+    /// Public key to start the next page from, if there are more items.
+    pub next: Option<PublicKey>,
+}
+
+impl<T> Page<T> {
+    /// Collects a page of at most `count` items from the iterator.
+    /// Empty pages cannot be requested.
+    fn collect<I>(iter: I, count: Option<usize>) -> api::Result<Self>
+    where
+        I: Iterator<Item = (PublicKey, T)>,
+    {
+        if count == Some(0) {
+            Err(api::Error::BadRequest("Page size must be positive".to_owned()))?
+        }
+        let count = count.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+        let mut items = Vec::new();
+        let mut next = None;
+        for (key, item) in iter {
+            if items.len() == count {
+                next = Some(key);
+                break;
+            }
+            items.push(item);
+        }
+        Ok(Page { items, next })
+    }
+}
+
+/// Maximal time in milliseconds a `wallet_events` request may wait for new blocks.
+pub const MAX_POLL_TIMEOUT: u64 = 30_000;
+
+/// The structure describes the query parameters for the `wallet_events` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct EventsQuery {
+    /// Public key of the watched wallet.
+    pub pub_key: PublicKey,
+    /// Height of the first block to return events from, as returned in `WalletEvents::next_height`.
+    pub from_height: u64,
+    /// Time in milliseconds to wait for new blocks if there are no events yet.
+    pub timeout: Option<u64>,
+}
+
+/// The structure describes the query parameters for the `events` endpoint.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct EventLogQuery {
+    /// Height of the first block to return events from.
+    pub from_height: u64,
+    /// Maximal number of events to return, rounded up to whole blocks.
+    pub count: Option<usize>,
+}
+
+/// The structure describes the query parameters for the `balance_at` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BalanceAtQuery {
+    /// Public key of the wallet.
+    pub pub_key: PublicKey,
+    /// Height of the block.
+    pub height: u64,
+}
+
+/// The structure describes the query parameters for the `wallet_statement` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct StatementQuery {
+    /// Public key of the wallet.
+    pub pub_key: PublicKey,
+    /// Start of the period, inclusive.
+    pub from: DateTime<Utc>,
+    /// End of the period, exclusive.
+    pub to: DateTime<Utc>,
+}
+
+/// Maximal number of hours covered by a `transactions_by_time` request.
+pub const MAX_TIME_RANGE_HOURS: u64 = 31 * 24;
+
+/// The structure describes the query parameters for the `transactions_by_time` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct TimeRangeQuery {
+    /// Start of the period, inclusive.
+    pub from: DateTime<Utc>,
+    /// End of the period, exclusive.
+    pub to: DateTime<Utc>,
+    /// Inspector of the post office to return the entries of, see `CurrencySchema::office_of`.
+    /// Entries of all post offices are returned by default.
+    pub office: Option<PublicKey>,
+}
+
+/// The structure describes the query parameters for the `export` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ExportQuery {
+    /// Start of the exported period, inclusive.
+    pub from: DateTime<Utc>,
+    /// End of the exported period, exclusive.
+    pub to: DateTime<Utc>,
+    /// Format of the export, CSV by default.
+    pub format: Option<ExportFormat>,
+}
+
+/// The structure describes the query parameters for the `verify_stamp` endpoint.
+/// Exactly one of the fields is expected.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct StampQuery {
+    /// Identifier of the stamp.
+    pub stamp: Option<Hash>,
+    /// Hash of the mail item paid with the stamp.
+    pub mail_item: Option<Hash>,
+}
+
+/// The structure describes the query parameters for the `mail_token` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct MailItemQuery {
+    /// Hash of the mail preparation transaction.
+    pub mail_item: Hash,
+}
+
+/// The structure describes the parameters of the `expire_preparations` endpoint.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExpirePreparationsQuery {
+    /// Maximal number of preparations to expire.
+    pub count: Option<usize>,
+}
+
+/// The structure describes the query parameters for the `index_timestamps` endpoint.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct IndexTimestampsQuery {
+    /// Maximal number of timestamps to process, `MAX_INDEX_BATCH` by default.
+    pub count: Option<u64>,
+}
+
 /// The structure returned by the REST API.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TransactionResponse {
@@ -52,6 +236,210 @@ pub struct WalletProof {
 pub struct WalletHistory {
     pub proof: ListProof<Hash>,
     pub transactions: Vec<WalletTransactions>,
+    /// Standing order payments, which are synthetic history entries without a transaction.
+    pub standing_order_payments: Vec<StandingOrderPayment>,
+}
+
+/// Committed change of a wallet.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletEvent {
+    /// Height of the block the change was committed in.
+    pub height: Height,
+    /// Hash of the transaction that made the change.
+    pub tx_hash: Hash,
+    /// Transaction that made the change.
+    pub transaction: WalletTransactions,
+    /// Payment made, if the change is a standing order payment.
+    pub standing_order_payment: Option<StandingOrderPayment>,
+}
+
+/// Wallet events since the requested height.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletEvents {
+    pub events: Vec<WalletEvent>,
+    /// Height to request the next events from.
+    pub next_height: u64,
+}
+
+/// Proof of the balances of a wallet after one of its history entries.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceProof {
+    /// Proof to the whole database table.
+    pub to_table: MapProof<Hash, Hash>,
+    /// Proof to the balances after the entry in this table, keyed by `balance_record_key`.
+    pub to_balance: MapProof<Hash, BalanceRecord>,
+}
+
+/// Balance of a wallet as of a past block.
+///
+/// Exonum keeps only the latest state, so the balance cannot be proven against the
+/// state hash of the past block directly. Instead, all proofs refer to the latest block:
+/// the wallet and its history are proven against its state hash, the history entries
+/// committed up to the requested height against the history of the wallet, and the
+/// balances recorded after the last of these entries against the state hash again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoricalBalance {
+    /// Block at the requested height, which the history entries are committed up to.
+    pub block_proof: BlockProof,
+    /// Latest block all the proofs below refer to.
+    pub latest_block_proof: BlockProof,
+    /// Proof of the latest wallet state, open or closed.
+    pub wallet_proof: WalletProof,
+    /// Number of history entries committed up to the requested height.
+    pub history_len: u64,
+    /// Proof of these history entries.
+    pub history_proof: Option<ListProof<Hash>>,
+    /// Proof of the balances after these entries, or of their absence if the entries
+    /// were made before balances were recorded.
+    pub balance_proof: Option<BalanceProof>,
+    /// Balances after these entries, or `None` if the wallet did not exist yet
+    /// or the balances are not recorded.
+    pub balance: Option<BalanceRecord>,
+}
+
+/// History entry of a wallet statement.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatementEntry {
+    /// Change of the balances made by the entry.
+    pub movement: Movement,
+    /// Transaction that made the entry.
+    pub transaction: Option<WalletTransactions>,
+    /// Payment made, if the entry is a standing order payment.
+    pub standing_order_payment: Option<StandingOrderPayment>,
+}
+
+/// Statement of a wallet for a period with running balances.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountStatement {
+    pub pub_key: PublicKey,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Available balance at the start of the period.
+    pub opening_balance: u64,
+    pub entries: Vec<StatementEntry>,
+    /// Available balance at the end of the period.
+    pub closing_balance: u64,
+}
+
+/// Part of the service event log.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventLog {
+    /// Proof to the whole database table.
+    pub to_table: MapProof<Hash, Hash>,
+    /// Proof of the returned events, if there are any.
+    pub proof: Option<ListProof<EventRecord>>,
+    pub events: Vec<EventRecord>,
+    /// Height to request the next events from, if there are more events.
+    pub next_height: Option<u64>,
+}
+
+/// Balances of a wallet in registered assets.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletAssets {
+    /// Proof to the whole database table.
+    pub to_table: MapProof<Hash, Hash>,
+    /// Proof of the non-zero balances of the wallet in registered assets.
+    pub proof: MapProof<Hash, AssetBalance>,
+    /// Non-zero balances. Postage units are the wallet balance and are not included.
+    pub balances: Vec<AssetBalance>,
+    /// Terms of the vouchers among the balances. Balances of expired vouchers are void.
+    pub vouchers: Vec<Voucher>,
+}
+
+/// Stamps owned by a wallet.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletStamps {
+    /// Proof to the whole database table.
+    pub to_table: MapProof<Hash, Hash>,
+    /// Proof of the owned stamps.
+    pub proof: MapProof<Hash, Stamp>,
+    /// Stamps owned by the wallet, both unused and used.
+    pub stamps: Vec<Stamp>,
+}
+
+/// Result of the stamp verification for sorting machines.
+///
+/// A stamp is good for delivery if it is `valid`, `unused` and `accepted`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StampVerification {
+    /// Latest block the proofs refer to.
+    pub block_proof: BlockProof,
+    /// Proof to the stamps table.
+    pub stamps_to_table: MapProof<Hash, Hash>,
+    /// Proof of the stamp.
+    pub stamp_proof: MapProof<Hash, Stamp>,
+    /// Proof to the redemptions table.
+    pub redemptions_to_table: MapProof<Hash, Hash>,
+    /// Proof of the redemption of the stamp or of its absence.
+    pub redemption_proof: MapProof<Hash, Redemption>,
+    /// The stamp, if it exists, as proven by `stamp_proof`.
+    pub stamp: Option<Stamp>,
+    /// The redemption of the stamp, if it is redeemed, as proven by `redemption_proof`.
+    pub redemption: Option<Redemption>,
+    /// The stamp exists and, if queried by the mail item, is used for it.
+    pub valid: bool,
+    /// The stamp is not redeemed yet.
+    pub unused: bool,
+    /// The mail item paid with the stamp is accepted by an inspector, that is the
+    /// `acceptance` of the proven stamp is set.
+    pub accepted: bool,
+}
+
+/// Postage token of a mail item for offline verification, see the `token` module.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MailToken {
+    /// Token encoded in base45.
+    pub token: String,
+}
+
+/// Result of the wallet lookup by name.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NameLookup {
+    /// Proof of the name directory entry.
+    pub name_proof: MapProof<Hash, NameRecord>,
+    /// Public key of the wallet the name resolves to, following key rotations.
+    pub pub_key: PublicKey,
+    /// Wallet the name resolves to.
+    pub wallet: Wallet,
+}
+
+/// Service health information for operators.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServiceHealth {
+    /// Height of the latest committed block.
+    pub height: Height,
+    /// Time reported by the time oracle, if any.
+    pub time: Option<DateTime<Utc>>,
+    /// Number of wallets.
+    pub wallets: usize,
+    /// Pending mail items.
+    pub pending_mail_items: PendingMailItems,
+    /// Number of transfers waiting for their execution time.
+    pub scheduled_transfers: usize,
+    /// Number of active standing orders.
+    pub standing_orders: usize,
+}
+
+/// Mail preparations waiting for acceptance.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingMailItems {
+    /// Number of pending preparations.
+    pub count: usize,
+    /// Number of pending preparations older than `PREPARATION_TTL`.
+    pub stale: usize,
+    /// Total amount frozen by pending preparations.
+    pub freezed_balance: u64,
+}
+
+/// Dump of role tables.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RolesDump {
+    pub inspectors: Vec<PublicKey>,
+    pub issuers: Vec<PublicKey>,
+    pub compliance_officers: Vec<PublicKey>,
+    pub sorting_machines: Vec<PublicKey>,
+    pub wallet_limits: Vec<(PublicKey, Limits)>,
+    pub role_limits: Vec<(u64, Limits)>,
 }
 
 /// Wallet information.
@@ -60,6 +448,10 @@ pub struct WalletInfo {
     pub block_proof: BlockProof,
     pub wallet_proof: WalletProof,
     pub wallet_history: Option<WalletHistory>,
+    /// Key the wallet has been moved to, if the queried key was rotated.
+    pub forwarded_to: Option<PublicKey>,
+    /// Proof of the final wallet state, if the wallet was closed.
+    pub closed_wallet_proof: Option<WalletProof>,
 }
 
 // TODO: Add documentation. (ECR-1638)
@@ -90,7 +482,19 @@ impl CryptocurrencyApi {
             to_wallet,
         };
 
-        let wallet = currency_schema.wallet(&query.pub_key);
+        let closed_wallet_proof = if currency_schema.closed_wallets().contains(&query.pub_key) {
+            // Closed wallets are the third table in the service state hash.
+            Some(WalletProof {
+                to_table: general_schema.get_proof_to_service_table(POST_SERVICE_ID, 2),
+                to_wallet: currency_schema.closed_wallets().get_proof(query.pub_key),
+            })
+        } else {
+            None
+        };
+
+        let wallet = currency_schema
+            .wallet(&query.pub_key)
+            .or_else(|| currency_schema.closed_wallets().get(&query.pub_key));
 
         let wallet_history = wallet.map(|_| {
             let history = currency_schema.wallet_history(&query.pub_key);
@@ -98,23 +502,692 @@ impl CryptocurrencyApi {
 
             let transactions: Vec<WalletTransactions> = history
                 .iter()
-                .map(|record| general_schema.transactions().get(&record).unwrap())
+                .filter_map(|record| general_schema.transactions().get(&record))
                 .map(|raw| WalletTransactions::tx_from_raw(raw).unwrap())
                 .collect::<Vec<_>>();
 
+            let standing_order_payments = history
+                .iter()
+                .filter_map(|record| currency_schema.standing_order_payments().get(&record))
+                .collect::<Vec<_>>();
+
             WalletHistory {
                 proof,
                 transactions,
+                standing_order_payments,
             }
         });
 
+        let forwarded_to = currency_schema.key_forwards().get(&query.pub_key);
+
         Ok(WalletInfo {
             block_proof,
             wallet_proof,
             wallet_history,
+            forwarded_to,
+            closed_wallet_proof,
+        })
+    }
+
+    fn events_since(state: &ServiceApiState, query: &EventsQuery) -> WalletEvents {
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        let currency_schema = CurrencySchema::new(&snapshot);
+
+        // Events of rotated keys are recorded in the history of the new key.
+        let pub_key = currency_schema
+            .forwarded_wallet(&query.pub_key)
+            .map_or(query.pub_key, |wallet| *wallet.pub_key());
+
+        let events = currency_schema
+            .wallet_history(&pub_key)
+            .iter()
+            .filter_map(|record| {
+                let payment = currency_schema.standing_order_payments().get(&record);
+                let tx_hash = payment.as_ref().map_or(record, |payment| *payment.tx_hash());
+                let location = general_schema.transactions_locations().get(&tx_hash)?;
+                if location.block_height().0 < query.from_height {
+                    return None;
+                }
+                let raw = general_schema.transactions().get(&tx_hash)?;
+                Some(WalletEvent {
+                    height: location.block_height(),
+                    tx_hash,
+                    transaction: WalletTransactions::tx_from_raw(raw).unwrap(),
+                    standing_order_payment: payment,
+                })
+            })
+            .collect();
+
+        WalletEvents {
+            events,
+            next_height: general_schema.block_hashes_by_height().len(),
+        }
+    }
+
+    /// Long-polls committed events of a wallet starting from `from_height`.
+    /// At most `notifier::MAX_WAITERS` requests wait at the same time, the rest return at once.
+    pub fn wallet_events(
+        state: &ServiceApiState,
+        query: EventsQuery,
+        notifier: &BlockNotifier,
+    ) -> api::Result<WalletEvents> {
+        let events = Self::events_since(state, &query);
+        let timeout = query.timeout.unwrap_or(0).min(MAX_POLL_TIMEOUT);
+        if !events.events.is_empty() || timeout == 0 {
+            return Ok(events);
+        }
+
+        notifier.wait_above(Height(events.next_height - 1), Duration::from_millis(timeout));
+        Ok(Self::events_since(state, &query))
+    }
+
+    pub fn events(state: &ServiceApiState, query: EventLogQuery) -> api::Result<EventLog> {
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        let currency_schema = CurrencySchema::new(&snapshot);
+        let events = currency_schema.events();
+
+        // Events are appended in block order, so the first one at `from_height`
+        // is found by bisection.
+        let (mut start, mut end) = (0, events.len());
+        while start < end {
+            let middle = (start + end) / 2;
+            if events.get(middle).unwrap().height() < query.from_height {
+                start = middle + 1;
+            } else {
+                end = middle;
+            }
+        }
+
+        // Events of one block are never split between pages.
+        let count = query.count.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+        let mut records: Vec<EventRecord> = Vec::new();
+        let mut next_height = None;
+        for record in events.iter_from(start) {
+            let last_height = records.last().map(EventRecord::height);
+            if records.len() >= count && last_height.map_or(false, |last| last != record.height()) {
+                next_height = Some(record.height());
+                break;
+            }
+            records.push(record);
+        }
+
+        let proof = if records.is_empty() {
+            None
+        } else {
+            Some(events.get_range_proof(start, start + records.len() as u64))
+        };
+
+        Ok(EventLog {
+            // The event log is the ninth table in the service state hash.
+            to_table: general_schema.get_proof_to_service_table(POST_SERVICE_ID, 8),
+            proof,
+            events: records,
+            next_height,
+        })
+    }
+
+    pub fn invoices_by_payer(
+        state: &ServiceApiState,
+        query: WalletQuery,
+    ) -> api::Result<Vec<Invoice>> {
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        let invoices = schema.invoices();
+        let pub_key = Self::current_key(&schema, &query.pub_key);
+        Ok(schema
+            .payer_invoices(&pub_key)
+            .iter()
+            .filter_map(|hash| invoices.get(&hash))
+            .collect())
+    }
+
+    pub fn invoices_by_payee(
+        state: &ServiceApiState,
+        query: WalletQuery,
+    ) -> api::Result<Vec<Invoice>> {
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        let invoices = schema.invoices();
+        let pub_key = Self::current_key(&schema, &query.pub_key);
+        Ok(schema
+            .payee_invoices(&pub_key)
+            .iter()
+            .filter_map(|hash| invoices.get(&hash))
+            .collect())
+    }
+
+    pub fn transfers_by_reference(
+        state: &ServiceApiState,
+        query: ReferenceQuery,
+    ) -> api::Result<Vec<TransferWithReference>> {
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        let currency_schema = CurrencySchema::new(&snapshot);
+        let transactions = general_schema.transactions();
+
+        currency_schema
+            .transfers_by_reference(&query.reference)
+            .iter()
+            .map(|hash| {
+                let raw = transactions.get(&hash).ok_or_else(|| {
+                    api::Error::NotFound(format!("Transaction {:?} not found", hash))
+                })?;
+                Message::from_raw(raw).map_err(|e| api::Error::InternalError(format_err!("{}", e)))
+            })
+            .collect()
+    }
+
+    pub fn lookup_wallet(state: &ServiceApiState, query: NameQuery) -> api::Result<NameLookup> {
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        let name = normalize_name(&query.name)
+            .ok_or_else(|| api::Error::BadRequest("Invalid name".to_owned()))?;
+
+        let name_hash = crypto::hash(name.as_bytes());
+        let record = schema
+            .names()
+            .get(&name_hash)
+            .ok_or_else(|| api::Error::NotFound("Name is not registered".to_owned()))?;
+        let wallet = schema
+            .forwarded_wallet(record.pub_key())
+            .ok_or_else(|| api::Error::NotFound("Wallet not found".to_owned()))?;
+
+        Ok(NameLookup {
+            name_proof: schema.names().get_proof(name_hash),
+            pub_key: *wallet.pub_key(),
+            wallet,
+        })
+    }
+
+    pub fn assets(state: &ServiceApiState, _query: ()) -> api::Result<Vec<Asset>> {
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        Ok(schema.assets().values().collect())
+    }
+
+    pub fn wallet_assets(state: &ServiceApiState, query: WalletQuery) -> api::Result<WalletAssets> {
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        let schema = CurrencySchema::new(&snapshot);
+        let keys = schema
+            .wallet_assets(&query.pub_key)
+            .iter()
+            .map(|asset| asset_balance_key(&query.pub_key, &asset))
+            .collect::<Vec<_>>();
+
+        let balances = schema.wallet_asset_balances(&query.pub_key);
+        let vouchers = balances
+            .iter()
+            .filter_map(|balance| schema.vouchers().get(balance.asset()))
+            .collect();
+
+        Ok(WalletAssets {
+            // Asset balances are the eleventh table in the service state hash.
+            to_table: general_schema.get_proof_to_service_table(POST_SERVICE_ID, 10),
+            proof: schema.asset_balances().get_multiproof(keys),
+            balances,
+            vouchers,
+        })
+    }
+
+    pub fn wallet_stamps(state: &ServiceApiState, query: WalletQuery) -> api::Result<WalletStamps> {
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        let schema = CurrencySchema::new(&snapshot);
+        let ids = schema.owned_stamps(&query.pub_key).iter().collect::<Vec<_>>();
+        let stamps = ids.iter().filter_map(|id| schema.stamps().get(id)).collect();
+
+        Ok(WalletStamps {
+            // Stamps are the thirteenth table in the service state hash.
+            to_table: general_schema.get_proof_to_service_table(POST_SERVICE_ID, 12),
+            proof: schema.stamps().get_multiproof(ids),
+            stamps,
+        })
+    }
+
+    pub fn verify_stamp(
+        state: &ServiceApiState,
+        query: StampQuery,
+    ) -> api::Result<StampVerification> {
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        let schema = CurrencySchema::new(&snapshot);
+
+        let id = match (query.stamp, query.mail_item) {
+            (Some(stamp), None) => stamp,
+            (None, Some(mail_item)) => schema
+                .stamp_preparations()
+                .get(&mail_item)
+                .ok_or_else(|| api::Error::NotFound("Mail item not found".to_owned()))?,
+            _ => Err(api::Error::BadRequest(
+                "Either stamp or mail_item is expected".to_owned(),
+            ))?,
+        };
+        let stamp = schema.stamps().get(&id);
+        let redemption = schema.redemptions().get(&id);
+        let valid = stamp.as_ref().map_or(false, |stamp| {
+            query.mail_item.map_or(true, |mail_item| *stamp.mail_item() == mail_item)
+        });
+        let accepted = valid && stamp.as_ref().map_or(false, Stamp::is_accepted);
+
+        let max_height = general_schema.block_hashes_by_height().len() - 1;
+        Ok(StampVerification {
+            block_proof: general_schema.block_and_precommits(Height(max_height)).unwrap(),
+            // Stamps and redemptions are the thirteenth and fourteenth tables
+            // in the service state hash.
+            stamps_to_table: general_schema.get_proof_to_service_table(POST_SERVICE_ID, 12),
+            stamp_proof: schema.stamps().get_proof(id),
+            redemptions_to_table: general_schema.get_proof_to_service_table(POST_SERVICE_ID, 13),
+            redemption_proof: schema.redemptions().get_proof(id),
+            unused: redemption.is_none(),
+            stamp,
+            redemption,
+            valid,
+            accepted,
+        })
+    }
+
+    pub fn mail_token(state: &ServiceApiState, query: MailItemQuery) -> api::Result<MailToken> {
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        let schema = CurrencySchema::new(&snapshot);
+
+        let raw = general_schema
+            .transactions()
+            .get(&query.mail_item)
+            .ok_or_else(|| api::Error::NotFound("Mail item not found".to_owned()))?;
+        let is_preparation = raw.service_id() == POST_SERVICE_ID
+            && match WalletTransactions::tx_from_raw(raw) {
+                Ok(WalletTransactions::MailPreparation(_))
+                | Ok(WalletTransactions::AssetMailPreparation(_))
+                | Ok(WalletTransactions::StampMailPreparation(_)) => true,
+                _ => false,
+            };
+        if !is_preparation {
+            Err(api::Error::BadRequest("Transaction is not a mail preparation".to_owned()))?
+        }
+        // Only successful preparations have a status.
+        if !schema.mail_statuses().contains(&query.mail_item) {
+            Err(api::Error::BadRequest("Mail preparation has failed".to_owned()))?
+        }
+
+        let token = PostageToken::new(&snapshot, &query.mail_item)
+            .ok_or_else(|| api::Error::NotFound("Mail item is not committed".to_owned()))?;
+        Ok(MailToken {
+            token: token.to_base45(),
+        })
+    }
+
+    pub fn validators(state: &ServiceApiState, _query: ()) -> api::Result<Vec<PublicKey>> {
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        Ok(general_schema
+            .actual_configuration()
+            .validator_keys
+            .iter()
+            .map(|keys| keys.consensus_key)
+            .collect())
+    }
+
+    pub fn wallets(state: &ServiceApiState, query: PageQuery) -> api::Result<Page<Wallet>> {
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        let wallets = schema.wallets();
+        let iter = match query.from {
+            Some(ref from) => wallets.iter_from(from),
+            None => wallets.iter(),
+        };
+        Page::collect(iter, query.count)
+    }
+
+    pub fn inspectors(state: &ServiceApiState, query: PageQuery) -> api::Result<Page<PublicKey>> {
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        let inspectors = schema.inspectors();
+        let iter = match query.from {
+            Some(ref from) => inspectors.keys_from(from),
+            None => inspectors.keys(),
+        };
+        Page::collect(iter.map(|key| (key, key)), query.count)
+    }
+
+    pub fn issuers(state: &ServiceApiState, query: PageQuery) -> api::Result<Page<PublicKey>> {
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        let issuers = schema.issuers();
+        let iter = match query.from {
+            Some(ref from) => issuers.keys_from(from),
+            None => issuers.keys(),
+        };
+        Page::collect(iter.map(|key| (key, key)), query.count)
+    }
+
+    fn pending_mail_items<T>(
+        schema: &CurrencySchema<T>,
+        now: Option<DateTime<Utc>>,
+    ) -> PendingMailItems
+    where
+        T: AsRef<dyn Snapshot>,
+    {
+        let timestamps = schema.timestamps();
+        let mut items = PendingMailItems {
+            count: 0,
+            stale: 0,
+            freezed_balance: 0,
+        };
+        for (pub_key, preparation) in schema.pending_preparations().iter() {
+            items.count += 1;
+            if let Some(wallet) = schema.wallet(&pub_key) {
+                items.freezed_balance += wallet.freezed_balance().0;
+            }
+            let prepared_at = timestamps.get(&preparation);
+            if let (Some(now), Some(prepared_at)) = (now, prepared_at) {
+                if now.timestamp() - prepared_at >= PREPARATION_TTL {
+                    items.stale += 1;
+                }
+            }
+        }
+        items
+    }
+
+    pub fn health(state: &ServiceApiState, _query: ()) -> api::Result<ServiceHealth> {
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        let schema = CurrencySchema::new(&snapshot);
+        let time = TimeSchema::new(&snapshot).time().get();
+
+        Ok(ServiceHealth {
+            height: Height(general_schema.block_hashes_by_height().len() - 1),
+            time,
+            wallets: schema.wallets().keys().count(),
+            pending_mail_items: Self::pending_mail_items(&schema, time),
+            scheduled_transfers: schema.scheduled_transfers().keys().count(),
+            standing_orders: schema.standing_orders().keys().count(),
+        })
+    }
+
+    pub fn pending_mail(state: &ServiceApiState, _query: ()) -> api::Result<PendingMailItems> {
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        let time = TimeSchema::new(&snapshot).time().get();
+        Ok(Self::pending_mail_items(&schema, time))
+    }
+
+    pub fn roles(state: &ServiceApiState, _query: ()) -> api::Result<RolesDump> {
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+
+        Ok(RolesDump {
+            inspectors: schema.inspectors().keys().collect(),
+            issuers: schema.issuers().keys().collect(),
+            compliance_officers: schema.compliance_officers().keys().collect(),
+            sorting_machines: schema.sorting_machines().keys().collect(),
+            wallet_limits: schema.wallet_limits().iter().collect(),
+            role_limits: schema.role_limits().iter().collect(),
         })
     }
 
+    /// Returns the statement of the wallet for the period with the transactions of its entries.
+    pub fn wallet_statement(
+        state: &ServiceApiState,
+        query: StatementQuery,
+    ) -> api::Result<AccountStatement> {
+        if query.from > query.to {
+            Err(api::Error::BadRequest("Period start is after its end".to_owned()))?
+        }
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        let currency_schema = CurrencySchema::new(&snapshot);
+
+        let wallet = currency_schema
+            .forwarded_wallet(&query.pub_key)
+            .or_else(|| currency_schema.closed_wallets().get(&query.pub_key))
+            .ok_or_else(|| api::Error::NotFound("Wallet not found".to_owned()))?;
+        let statement = export::statement(&currency_schema, &wallet, query.from, query.to);
+
+        let entries = statement
+            .movements
+            .into_iter()
+            .map(|movement| {
+                let payment = currency_schema
+                    .standing_order_payments()
+                    .get(&movement.tx_hash);
+                let tx_hash = payment
+                    .as_ref()
+                    .map_or(movement.tx_hash, |payment| *payment.tx_hash());
+                let transaction = general_schema
+                    .transactions()
+                    .get(&tx_hash)
+                    .map(|raw| WalletTransactions::tx_from_raw(raw).unwrap());
+                StatementEntry {
+                    movement,
+                    transaction,
+                    standing_order_payment: payment,
+                }
+            })
+            .collect();
+
+        Ok(AccountStatement {
+            pub_key: statement.pub_key,
+            from: statement.from,
+            to: statement.to,
+            opening_balance: statement.opening_balance,
+            entries,
+            closing_balance: statement.closing_balance,
+        })
+    }
+
+    /// Returns the current key of the wallet, following forwarding records left by
+    /// key rotations.
+    fn current_key<T>(schema: &CurrencySchema<T>, pub_key: &PublicKey) -> PublicKey
+    where
+        T: AsRef<dyn Snapshot>,
+    {
+        schema
+            .forwarded_wallet(pub_key)
+            .map_or(*pub_key, |wallet| *wallet.pub_key())
+    }
+
+    /// Returns the height of the block containing the transaction that made the history entry.
+    pub fn balance_at(
+        state: &ServiceApiState,
+        query: BalanceAtQuery,
+    ) -> api::Result<HistoricalBalance> {
+        let snapshot = state.snapshot();
+        let general_schema = blockchain::Schema::new(&snapshot);
+        let currency_schema = CurrencySchema::new(&snapshot);
+
+        let max_height = general_schema.block_hashes_by_height().len() - 1;
+        if query.height > max_height {
+            Err(api::Error::BadRequest("Block is not committed yet".to_owned()))?
+        }
+        let block_proof = general_schema
+            .block_and_precommits(Height(query.height))
+            .unwrap();
+        let latest_block_proof = general_schema
+            .block_and_precommits(Height(max_height))
+            .unwrap();
+
+        let wallet = currency_schema
+            .forwarded_wallet(&query.pub_key)
+            .or_else(|| currency_schema.closed_wallets().get(&query.pub_key))
+            .ok_or_else(|| api::Error::NotFound("Wallet not found".to_owned()))?;
+        let pub_key = *wallet.pub_key();
+        // Open wallets are the first table in the service state hash, closed ones are the third.
+        let wallet_proof = if currency_schema.wallets().contains(&pub_key) {
+            WalletProof {
+                to_table: general_schema.get_proof_to_service_table(POST_SERVICE_ID, 0),
+                to_wallet: currency_schema.wallets().get_proof(pub_key),
+            }
+        } else {
+            WalletProof {
+                to_table: general_schema.get_proof_to_service_table(POST_SERVICE_ID, 2),
+                to_wallet: currency_schema.closed_wallets().get_proof(pub_key),
+            }
+        };
+
+        // History entries are committed in block order, so the ones up to the height
+        // form a prefix found by bisection.
+        let history = currency_schema.wallet_history(&pub_key);
+        let (mut start, mut end) = (0, history.len());
+        while start < end {
+            let middle = (start + end) / 2;
+            let record = history.get(middle).unwrap();
+            let height = currency_schema.entry_height(&record);
+            if height.map_or(false, |height| height.0 <= query.height) {
+                start = middle + 1;
+            } else {
+                end = middle;
+            }
+        }
+        let history_len = start;
+
+        let (history_proof, balance_proof, balance) = if history_len == 0 {
+            (None, None, None)
+        } else {
+            let key = balance_record_key(&pub_key, history_len - 1);
+            // Balance records are the fifteenth table in the service state hash.
+            let balance_proof = BalanceProof {
+                to_table: general_schema.get_proof_to_service_table(POST_SERVICE_ID, 14),
+                to_balance: currency_schema.balance_records().get_proof(key),
+            };
+            (
+                Some(history.get_range_proof(0, history_len)),
+                Some(balance_proof),
+                currency_schema.balance_records().get(&key),
+            )
+        };
+
+        Ok(HistoricalBalance {
+            block_proof,
+            latest_block_proof,
+            wallet_proof,
+            history_len,
+            history_proof,
+            balance_proof,
+            balance,
+        })
+    }
+
+    /// Returns timestamped transactions and history entries within the period.
+    pub fn transactions_by_time(
+        state: &ServiceApiState,
+        query: TimeRangeQuery,
+    ) -> api::Result<Vec<TimeIndexEntry>> {
+        if query.from > query.to {
+            Err(api::Error::BadRequest("Period start is after its end".to_owned()))?
+        }
+        let (first_hour, last_hour) = (hour_bucket(query.from), hour_bucket(query.to));
+        if last_hour - first_hour >= MAX_TIME_RANGE_HOURS {
+            Err(api::Error::BadRequest("Period is too long".to_owned()))?
+        }
+
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        let entries = (first_hour..=last_hour)
+            .flat_map(|hour| match query.office {
+                Some(ref office) => schema.office_time_index(office, hour).iter().collect(),
+                None => schema.time_index(hour).iter().collect::<Vec<_>>(),
+            })
+            .filter(|entry| entry.time() >= query.from && entry.time() < query.to)
+            .collect();
+        Ok(entries)
+    }
+
+    /// Exports statements of all wallets for the period, rendered as a single document.
+    pub fn export(state: &ServiceApiState, query: ExportQuery) -> api::Result<String> {
+        if query.from > query.to {
+            Err(api::Error::BadRequest("Period start is after its end".to_owned()))?
+        }
+        let snapshot = state.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        let statements = export::statements(&schema, query.from, query.to);
+        Ok(export::render(&statements, query.format.unwrap_or_default()))
+    }
+
+    /// Serves the export with the content type of its format rather than as a JSON string.
+    fn export_handler(request: HttpRequest) -> FutureResponse {
+        let response = Query::<ExportQuery>::extract(&request).and_then(|query| {
+            let query = query.into_inner();
+            let format = query.format.unwrap_or_default();
+            let body = Self::export(request.state(), query)?;
+            Ok(HttpResponse::Ok().content_type(format.content_type()).body(body))
+        });
+        Box::new(future::result(response))
+    }
+
+    /// Sends `ExpirePreparation` transactions signed by this node for stale preparations.
+    pub fn expire_preparations(
+        state: &ServiceApiState,
+        query: ExpirePreparationsQuery,
+    ) -> api::Result<Vec<TransactionResponse>> {
+        let stale = {
+            let snapshot = state.snapshot();
+            let schema = CurrencySchema::new(&snapshot);
+            let now = match TimeSchema::new(&snapshot).time().get() {
+                Some(now) => now,
+                None => return Ok(Vec::new()),
+            };
+            let timestamps = schema.timestamps();
+            schema
+                .pending_preparations()
+                .iter()
+                .filter(|(_, preparation)| {
+                    timestamps.get(preparation).map_or(false, |prepared_at| {
+                        now.timestamp() - prepared_at >= PREPARATION_TTL
+                    })
+                })
+                .map(|(pub_key, _)| pub_key)
+                .take(query.count.unwrap_or(DEFAULT_PAGE_SIZE))
+                .collect::<Vec<_>>()
+        };
+
+        let seed = blockchain::Schema::new(&state.snapshot())
+            .block_hashes_by_height()
+            .len();
+        let mut responses = Vec::new();
+        for pub_key in stale {
+            let transaction: Box<dyn Transaction> = Box::new(ExpirePreparation::new(
+                state.public_key(),
+                &pub_key,
+                seed,
+                state.secret_key(),
+            ));
+            let tx_hash = transaction.hash();
+            state.sender().send(transaction)?;
+            responses.push(TransactionResponse { tx_hash });
+        }
+        Ok(responses)
+    }
+
+    /// Sends an `IndexTimestamps` transaction signed by this node to add entries timestamped
+    /// before the time index was introduced to the index.
+    pub fn index_timestamps(
+        state: &ServiceApiState,
+        query: IndexTimestampsQuery,
+    ) -> api::Result<TransactionResponse> {
+        let seed = blockchain::Schema::new(&state.snapshot())
+            .block_hashes_by_height()
+            .len();
+        let count = query.count.unwrap_or(MAX_INDEX_BATCH);
+        let transaction: Box<dyn Transaction> = Box::new(IndexTimestamps::new(
+            state.public_key(),
+            count,
+            seed,
+            state.secret_key(),
+        ));
+        let tx_hash = transaction.hash();
+        state.sender().send(transaction)?;
+        Ok(TransactionResponse { tx_hash })
+    }
+
+    /// Returns the catalogue of transaction error codes.
+    pub fn errors(_state: &ServiceApiState, _query: ()) -> api::Result<Vec<ErrorDescription>> {
+        Ok(Error::catalogue())
+    }
+
     pub fn post_transaction(
         state: &ServiceApiState,
         query: WalletTransactions,
@@ -125,10 +1198,45 @@ impl CryptocurrencyApi {
         Ok(TransactionResponse { tx_hash })
     }
 
-    pub fn wire(builder: &mut ServiceApiBuilder) {
+    pub fn wire(builder: &mut ServiceApiBuilder, notifier: Arc<BlockNotifier>) {
         builder
             .public_scope()
             .endpoint("v1/wallets/info", Self::wallet_info)
+            .endpoint("v1/wallets/events", move |state: &ServiceApiState, query| {
+                Self::wallet_events(state, query, &notifier)
+            })
+            .endpoint("v1/wallets/lookup", Self::lookup_wallet)
+            .endpoint("v1/wallets/statement", Self::wallet_statement)
+            .endpoint("v1/wallets/balance-at", Self::balance_at)
+            .endpoint("v1/wallets/assets", Self::wallet_assets)
+            .endpoint("v1/wallets/stamps", Self::wallet_stamps)
+            .endpoint("v1/stamps/verify", Self::verify_stamp)
+            .endpoint("v1/mail/token", Self::mail_token)
+            .endpoint("v1/validators", Self::validators)
+            .endpoint("v1/assets", Self::assets)
+            .endpoint("v1/invoices/by-payer", Self::invoices_by_payer)
+            .endpoint("v1/invoices/by-payee", Self::invoices_by_payee)
+            .endpoint("v1/transfers/by-reference", Self::transfers_by_reference)
+            .endpoint("v1/events", Self::events)
+            .endpoint("v1/transactions/by-time", Self::transactions_by_time)
+            .endpoint("v1/errors", Self::errors)
+            .endpoint("v1/inspectors", Self::inspectors)
+            .endpoint("v1/issuers", Self::issuers)
             .endpoint_mut("v1/wallets/transaction", Self::post_transaction);
+        // Listing all wallets discloses the customer base and is available to operators only.
+        builder
+            .private_scope()
+            .endpoint("v1/wallets", Self::wallets)
+            .endpoint("v1/health", Self::health)
+            .endpoint("v1/mail/pending", Self::pending_mail)
+            .endpoint("v1/roles", Self::roles)
+            .endpoint_mut("v1/maintenance/expire-preparations", Self::expire_preparations)
+            .endpoint_mut("v1/maintenance/index-timestamps", Self::index_timestamps)
+            .web_backend()
+            .raw_handler(RequestHandler {
+                name: "v1/export".to_owned(),
+                method: Method::GET,
+                inner: Arc::new(Self::export_handler),
+            });
     }
 }
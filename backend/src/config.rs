@@ -0,0 +1,77 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Service configuration stored in the blockchain configuration.
+
+use exonum::{
+    blockchain, helpers::fabric::{keys, Context}, storage::Snapshot,
+};
+use serde_json;
+
+use SERVICE_NAME;
+
+/// Behaviour of time dependent transactions before the time oracle has reported the time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimePolicy {
+    /// Reject transactions with the `TimeNotAvailable` error.
+    Reject,
+    /// Use the Unix epoch plus the height of the block in seconds as the time,
+    /// so that timestamps are ordered by blocks until the oracle reports.
+    BlockHeight,
+}
+
+impl Default for TimePolicy {
+    fn default() -> Self {
+        TimePolicy::Reject
+    }
+}
+
+/// Configuration of the service.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceConfig {
+    #[serde(default)]
+    pub time_policy: TimePolicy,
+}
+
+impl ServiceConfig {
+    /// Reads the genesis configuration from the `cryptocurrency` section of `services_configs`
+    /// in the node configuration, or returns the default one if the section is missing.
+    ///
+    /// # Panics
+    ///
+    /// If the section cannot be parsed, so that a node does not start with a wrong genesis.
+    pub fn from_context(context: &Context) -> Self {
+        context
+            .get(keys::NODE_CONFIG)
+            .ok()
+            .and_then(|node_config| node_config.services_configs.get(SERVICE_NAME).cloned())
+            .map(|value| {
+                value
+                    .try_into()
+                    .expect("Invalid configuration of the cryptocurrency service")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the actual configuration of the service, or the default one if none is stored.
+    pub fn actual<T: AsRef<dyn Snapshot>>(view: T) -> Self {
+        blockchain::Schema::new(view)
+            .actual_configuration()
+            .services
+            .get(SERVICE_NAME)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Structured events emitted by the service transactions.
+
+use exonum::{crypto::{Hash, PublicKey}, helpers::Height};
+use serde_json;
+
+use chrono::{DateTime, Utc};
+
+use amount::Amount;
+
+/// Event emitted by a successfully executed transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PostEvent {
+    /// A wallet was created.
+    WalletCreated { pub_key: PublicKey, name: String },
+    /// A role was assigned to a wallet: 1 - inspector, 2 - issuer, 3 - compliance officer,
+    /// 4 - sorting machine.
+    RoleAssigned { pub_key: PublicKey, role: u64 },
+    /// Funds were moved from one wallet to another.
+    ///
+    /// Here and below `asset` is the zero hash for postage units.
+    Transferred {
+        from: PublicKey,
+        to: PublicKey,
+        asset: Hash,
+        amount: Amount,
+    },
+    /// Funds were issued to a wallet.
+    Issued {
+        issuer: PublicKey,
+        pub_key: PublicKey,
+        asset: Hash,
+        amount: Amount,
+    },
+    /// Funds were frozen for a mail item. For stamped mail items here and below
+    /// `asset` is the stamp identifier and `amount` is its denomination.
+    MailPrepared {
+        pub_key: PublicKey,
+        asset: Hash,
+        amount: Amount,
+    },
+    /// A mail item was accepted by an inspector.
+    MailAccepted {
+        pub_key: PublicKey,
+        asset: Hash,
+        amount: Amount,
+    },
+    /// A mail item was rejected by an inspector and its funds returned.
+    MailRejected {
+        pub_key: PublicKey,
+        asset: Hash,
+        amount: Amount,
+    },
+    /// A stale mail preparation was expired and its funds returned.
+    MailExpired {
+        pub_key: PublicKey,
+        asset: Hash,
+        amount: Amount,
+    },
+    /// A transaction was cancelled by an inspector.
+    Cancelled { inspector: PublicKey, tx_hash: Hash },
+    /// A wallet moved to a new key.
+    KeyRotated { old_key: PublicKey, new_key: PublicKey },
+    /// A wallet was suspended or unsuspended by a compliance officer.
+    SuspensionChanged { pub_key: PublicKey, suspended: bool },
+    /// A wallet was closed and its balance, including funds reserved by cancelled
+    /// scheduled transfers, swept to the beneficiary. Voucher balances are voided.
+    WalletClosed {
+        pub_key: PublicKey,
+        beneficiary: PublicKey,
+        amount: Amount,
+    },
+    /// Spending limits of a wallet were changed.
+    WalletLimitsSet { pub_key: PublicKey },
+    /// Spending limits of a role were changed.
+    RoleLimitsSet { role: u64 },
+    /// Funds were reserved for a transfer at a future time.
+    TransferScheduled {
+        from: PublicKey,
+        to: PublicKey,
+        amount: Amount,
+        execute_at: DateTime<Utc>,
+    },
+    /// A standing order was created.
+    StandingOrderCreated { order: Hash },
+    /// A standing order was cancelled by its payer.
+    StandingOrderCancelled { order: Hash },
+    /// An invoice was issued.
+    InvoiceCreated { invoice: Hash },
+    /// An invoice was paid.
+    InvoicePaid { invoice: Hash },
+    /// A name was registered in the wallet name directory.
+    NameRegistered { name: String, pub_key: PublicKey },
+    /// A name was released.
+    NameReleased { name: String },
+    /// An asset was registered.
+    AssetCreated {
+        asset: Hash,
+        issuer: PublicKey,
+        name: String,
+    },
+    /// A registered asset was made a voucher expiring at the given time.
+    VoucherCreated {
+        asset: Hash,
+        expires_at: DateTime<Utc>,
+    },
+    /// Stamps with consecutive serial numbers were minted.
+    StampsMinted {
+        issuer: PublicKey,
+        owner: PublicKey,
+        series: String,
+        first_number: u64,
+        count: u64,
+    },
+    /// A stamp was moved from one wallet to another.
+    StampTransferred {
+        stamp: Hash,
+        from: PublicKey,
+        to: PublicKey,
+    },
+    /// A stamp of an accepted mail item was redeemed by a sorting machine.
+    StampRedeemed {
+        stamp: Hash,
+        mail_item: Hash,
+        machine: PublicKey,
+    },
+    /// A voucher balance of a closed wallet was voided.
+    VoucherVoided {
+        pub_key: PublicKey,
+        asset: Hash,
+        amount: Amount,
+    },
+    /// Funds reserved for a scheduled transfer were returned to the sender,
+    /// because the receiver has closed its wallet.
+    TransferRefunded {
+        transfer: Hash,
+        pub_key: PublicKey,
+        amount: Amount,
+    },
+}
+
+encoding_struct! {
+    /// Event together with the block and the transaction that emitted it.
+    struct EventRecord {
+        /// Height of the block containing the transaction.
+        height: u64,
+        /// Hash of the transaction.
+        tx_hash: &Hash,
+        /// Emitted event as JSON, so new variants don't require a new storage format.
+        payload: &str,
+    }
+}
+
+impl EventRecord {
+    /// Creates a record of the `event` emitted at the given height.
+    pub fn from_event(height: Height, tx_hash: &Hash, event: &PostEvent) -> Self {
+        let payload = serde_json::to_string(event).expect("Events are serializable");
+        EventRecord::new(height.0, tx_hash, &payload)
+    }
+
+    /// Returns the emitted event.
+    pub fn event(&self) -> PostEvent {
+        serde_json::from_str(self.payload()).expect("Events are stored by the service")
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Ledger export for accounting.
+
+use exonum::{
+    crypto::{Hash, PublicKey}, encoding::serialize::encode_hex, storage::Snapshot,
+};
+use serde_json;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use std::str::FromStr;
+
+use wallet::Wallet;
+use CurrencySchema;
+
+/// Format of the exported ledger.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    /// Comma-separated values, one row per opening balance, movement and closing balance.
+    #[serde(rename = "csv")]
+    Csv,
+    /// One JSON statement per line.
+    #[serde(rename = "jsonl")]
+    JsonLines,
+}
+
+impl Default for ExportFormat {
+    fn default() -> Self {
+        ExportFormat::Csv
+    }
+}
+
+impl ExportFormat {
+    /// Returns the HTTP content type of the format.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::JsonLines => "application/x-ndjson",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" => Ok(ExportFormat::JsonLines),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
+    }
+}
+
+/// Change of the wallet balance made by a history entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Movement {
+    /// Hash of the history entry.
+    pub tx_hash: Hash,
+    /// Time of the entry.
+    pub time: DateTime<Utc>,
+    /// Signed change of the available balance.
+    pub amount: i64,
+    /// Available balance after the entry.
+    pub balance: u64,
+    /// Frozen balance after the entry.
+    pub freezed_balance: u64,
+}
+
+/// Statement of a wallet for the period `[from, to)`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Statement {
+    pub pub_key: PublicKey,
+    pub name: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Available balance at the start of the period.
+    pub opening_balance: u64,
+    pub movements: Vec<Movement>,
+    /// Available balance at the end of the period.
+    pub closing_balance: u64,
+}
+
+/// Builds the statement of the wallet for the period `[from, to)`.
+///
+/// Entries without a timestamp are considered to happen at the time of the previous entry.
+/// Entries without a balance record, made before balances were recorded, are skipped,
+/// and the balance recorded after them is only used as the opening balance.
+pub fn statement<T>(
+    schema: &CurrencySchema<T>,
+    wallet: &Wallet,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Statement
+where
+    T: AsRef<dyn Snapshot>,
+{
+    let history = schema.wallet_history(wallet.pub_key());
+    let timestamps = schema.timestamps();
+
+    let mut opening_balance = 0;
+    let mut movements = Vec::new();
+    let mut previous = Some(0);
+    let mut last_time = None;
+    for (index, tx_hash) in history.iter().enumerate() {
+        let time = timestamps
+            .get(&tx_hash)
+            .map(|seconds| Utc.timestamp(seconds, 0))
+            .or(last_time);
+        last_time = time;
+        let record = match schema.wallet_balance(wallet.pub_key(), index as u64) {
+            Some(record) => record,
+            None => {
+                previous = None;
+                continue;
+            }
+        };
+
+        let amount = previous.map(|previous| record.balance().0 as i64 - previous as i64);
+        previous = Some(record.balance().0);
+        match (time, amount) {
+            (Some(time), _) if time >= to => break,
+            (Some(time), Some(amount)) if time >= from => movements.push(Movement {
+                tx_hash,
+                time,
+                amount,
+                balance: record.balance().0,
+                freezed_balance: record.freezed_balance().0,
+            }),
+            _ => opening_balance = record.balance().0,
+        }
+    }
+    let closing_balance = movements
+        .last()
+        .map_or(opening_balance, |movement| movement.balance);
+
+    Statement {
+        pub_key: *wallet.pub_key(),
+        name: wallet.name().to_owned(),
+        from,
+        to,
+        opening_balance,
+        movements,
+        closing_balance,
+    }
+}
+
+/// Builds statements of all open and closed wallets for the period `[from, to)`.
+pub fn statements<T>(
+    schema: &CurrencySchema<T>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<Statement>
+where
+    T: AsRef<dyn Snapshot>,
+{
+    schema
+        .wallets()
+        .values()
+        .chain(schema.closed_wallets().values())
+        .map(|wallet| statement(schema, &wallet, from, to))
+        .collect()
+}
+
+/// Quotes a CSV field if needed.
+fn csv_field(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Renders statements in the given format.
+pub fn render(statements: &[Statement], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Csv => to_csv(statements),
+        ExportFormat::JsonLines => to_json_lines(statements),
+    }
+}
+
+/// Renders statements as CSV.
+pub fn to_csv(statements: &[Statement]) -> String {
+    let mut out = String::from("wallet,name,entry,time,tx_hash,amount,balance,freezed_balance\n");
+    for statement in statements {
+        let prefix = format!("{},{}", encode_hex(statement.pub_key), csv_field(&statement.name));
+        out += &format!(
+            "{},opening,{},,,{},\n",
+            prefix,
+            statement.from.to_rfc3339(),
+            statement.opening_balance
+        );
+        for movement in &statement.movements {
+            out += &format!(
+                "{},movement,{},{},{},{},{}\n",
+                prefix,
+                movement.time.to_rfc3339(),
+                encode_hex(movement.tx_hash),
+                movement.amount,
+                movement.balance,
+                movement.freezed_balance
+            );
+        }
+        out += &format!(
+            "{},closing,{},,,{},\n",
+            prefix,
+            statement.to.to_rfc3339(),
+            statement.closing_balance
+        );
+    }
+    out
+}
+
+/// Renders statements as JSON Lines.
+pub fn to_json_lines(statements: &[Statement]) -> String {
+    let mut out = String::new();
+    for statement in statements {
+        out += &serde_json::to_string(statement).unwrap();
+        out.push('\n');
+    }
+    out
+}
@@ -16,41 +16,82 @@
 
 #![deny(missing_debug_implementations, unsafe_code, bare_trait_objects)]
 
+extern crate actix_web;
 #[macro_use]
 extern crate exonum;
 extern crate exonum_time;
 #[macro_use]
 extern crate failure;
+extern crate futures;
 extern crate serde;
+extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
 extern crate chrono;
+#[macro_use]
+extern crate log;
+extern crate unicode_normalization;
 
 pub use schema::CurrencySchema;
 
+pub mod amount;
 pub mod api;
+pub mod config;
+pub mod events;
+pub mod export;
+pub mod notifier;
 pub mod schema;
+pub mod token;
 pub mod transactions;
 pub mod wallet;
 
 use exonum::{
-    api::ServiceApiBuilder, blockchain::{Service, Transaction, TransactionSet}, crypto::Hash,
-    encoding::Error as EncodingError, helpers::fabric::{self, Context}, messages::RawTransaction,
-    storage::Snapshot,
+    api::ServiceApiBuilder,
+    blockchain::{self, Service, ServiceContext, Transaction, TransactionSet},
+    crypto::Hash, encoding::Error as EncodingError, helpers::fabric::{self, Context},
+    messages::RawTransaction, storage::{Fork, Snapshot},
 };
+use exonum_time::schema::TimeSchema;
+
+use std::sync::Arc;
 
-use transactions::WalletTransactions;
+use amount::Amount;
+use config::ServiceConfig;
+use notifier::BlockNotifier;
+use transactions::{ExecuteScheduled, ExecuteStandingOrder, WalletTransactions};
 
 /// Unique service ID.
 const POST_SERVICE_ID: u16 = 128;
 /// Name of the service.
 pub const SERVICE_NAME: &str = "cryptocurrency";
 /// Initial balance of the wallet.
-const INITIAL_BALANCE: u64 = 100;
+const INITIAL_BALANCE: Amount = Amount(100);
+/// Time in seconds after which a mail preparation that is neither accepted
+/// nor rejected may be expired.
+pub const PREPARATION_TTL: i64 = 7 * 24 * 3600;
+/// Maximal number of stamps minted by a single `MintStamps` transaction.
+pub const MAX_MINT_BATCH: u64 = 1000;
+/// Maximal number of timestamps processed by a single `IndexTimestamps` transaction.
+pub const MAX_INDEX_BATCH: u64 = 1000;
 
 /// Exonum `Service` implementation.
 #[derive(Default, Debug)]
-pub struct CurrencyService;
+pub struct CurrencyService {
+    /// Configuration stored in the genesis block.
+    config: ServiceConfig,
+    /// Wakes up API requests waiting for new blocks.
+    notifier: Arc<BlockNotifier>,
+}
+
+impl CurrencyService {
+    /// Creates the service with the given genesis configuration.
+    pub fn with_config(config: ServiceConfig) -> Self {
+        CurrencyService {
+            config,
+            ..Default::default()
+        }
+    }
+}
 
 impl Service for CurrencyService {
     fn service_name(&self) -> &str {
@@ -66,12 +107,60 @@ impl Service for CurrencyService {
         schema.state_hash()
     }
 
+    fn initialize(&self, _fork: &mut Fork) -> serde_json::Value {
+        serde_json::to_value(&self.config).unwrap()
+    }
+
     fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, EncodingError> {
         WalletTransactions::tx_from_raw(raw).map(Into::into)
     }
 
     fn wire_api(&self, builder: &mut ServiceApiBuilder) {
-        api::CryptocurrencyApi::wire(builder);
+        api::CryptocurrencyApi::wire(builder, Arc::clone(&self.notifier));
+    }
+
+    fn handle_commit(&self, context: &ServiceContext) {
+        // `context.height()` is the height of the next block.
+        self.notifier.notify(context.height().previous());
+
+        // Due scheduled transfers and standing order payments are executed by transactions
+        // signed by validators. Validators take turns by height, so that a due item is sent
+        // by a single validator.
+        let validator_id = match context.validator_id() {
+            Some(validator_id) => validator_id,
+            None => return,
+        };
+        let validators = blockchain::Schema::new(context.snapshot())
+            .actual_configuration()
+            .validator_keys
+            .len() as u64;
+        if context.height().0 % validators != u64::from(validator_id.0) {
+            return;
+        }
+        let time = match TimeSchema::new(context.snapshot()).time().get() {
+            Some(time) => time,
+            None => return,
+        };
+
+        let (pub_key, sec_key) = (context.public_key(), context.secret_key());
+        let seed = context.height().0;
+        let schema = CurrencySchema::new(context.snapshot());
+        let mut txs: Vec<Box<dyn Transaction>> = Vec::new();
+        for tx_hash in schema.due_until(time) {
+            if schema.scheduled_transfers().contains(&tx_hash) {
+                let tx = ExecuteScheduled::new(pub_key, &tx_hash, seed, sec_key);
+                txs.push(Box::new(tx));
+            } else {
+                let tx = ExecuteStandingOrder::new(pub_key, &tx_hash, seed, sec_key);
+                txs.push(Box::new(tx));
+            }
+        }
+
+        for tx in txs {
+            if let Err(e) = context.transaction_sender().send(tx) {
+                error!("Couldn't send transaction from handle_commit: {}", e);
+            }
+        }
     }
 }
 
@@ -83,7 +172,7 @@ impl fabric::ServiceFactory for ServiceFactory {
         SERVICE_NAME
     }
 
-    fn make_service(&mut self, _: &Context) -> Box<dyn Service> {
-        Box::new(CurrencyService)
+    fn make_service(&mut self, context: &Context) -> Box<dyn Service> {
+        Box::new(CurrencyService::with_config(ServiceConfig::from_context(context)))
     }
 }
@@ -12,19 +12,73 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+extern crate chrono;
 extern crate exonum;
 extern crate exonum_configuration;
 extern crate exonum_russian_post;
 extern crate exonum_time;
 
-use exonum::helpers::{self, fabric::NodeBuilder};
+use chrono::{DateTime, Utc};
+use exonum::{
+    helpers::{self, fabric::NodeBuilder}, storage::{Database, DbOptions, RocksDB},
+};
 use exonum_configuration as configuration;
 use exonum_russian_post as cryptocurrency;
 use exonum_time::TimeServiceFactory;
 
+use cryptocurrency::{export::{self, ExportFormat}, CurrencySchema};
+
+use std::{env, process};
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| format!("Invalid time {}: {}", value, e))
+}
+
+/// Prints statements of all wallets for a period read from the node database.
+/// The node must be stopped, or a copy of its database used.
+///
+/// Usage: `export --db-path <path> --from <RFC 3339> --to <RFC 3339> [--format csv|jsonl]`
+fn run_export(args: &[String]) -> Result<(), String> {
+    let (mut db_path, mut from, mut to) = (None, None, None);
+    let mut format = ExportFormat::default();
+    for pair in args.chunks(2) {
+        let value = pair
+            .get(1)
+            .ok_or_else(|| format!("Missing value of {}", pair[0]))?;
+        match pair[0].as_str() {
+            "--db-path" => db_path = Some(value.clone()),
+            "--from" => from = Some(parse_time(value)?),
+            "--to" => to = Some(parse_time(value)?),
+            "--format" => format = value.parse()?,
+            other => return Err(format!("Unknown argument {}", other)),
+        }
+    }
+    let db_path = db_path.ok_or_else(|| "Missing --db-path".to_owned())?;
+    let from = from.ok_or_else(|| "Missing --from".to_owned())?;
+    let to = to.ok_or_else(|| "Missing --to".to_owned())?;
+
+    let db = RocksDB::open(&db_path, &DbOptions::default()).map_err(|e| e.to_string())?;
+    let snapshot = db.snapshot();
+    let schema = CurrencySchema::new(&snapshot);
+    let statements = export::statements(&schema, from, to);
+    print!("{}", export::render(&statements, format));
+    Ok(())
+}
 
 fn main() {
     exonum::crypto::init();
+
+    let args = env::args().collect::<Vec<_>>();
+    if args.get(1).map(String::as_str) == Some("export") {
+        if let Err(e) = run_export(&args[2..]) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
     helpers::init_logger().unwrap();
 
     let node = NodeBuilder::new()
@@ -0,0 +1,72 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wake-ups for API handlers waiting for new blocks.
+
+use exonum::helpers::Height;
+
+use std::{
+    sync::{Condvar, Mutex}, time::{Duration, Instant},
+};
+
+/// Maximal number of requests waiting for new blocks at the same time. Every waiting
+/// request occupies an API worker thread, so requests above the limit return at once.
+pub const MAX_WAITERS: usize = 4;
+
+#[derive(Debug, Default)]
+struct State {
+    height: u64,
+    waiters: usize,
+}
+
+/// Keeps the height of the latest committed block and wakes up long-poll
+/// requests when it changes.
+#[derive(Debug, Default)]
+pub struct BlockNotifier {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+impl BlockNotifier {
+    /// Records a committed block and wakes up all waiting requests.
+    pub fn notify(&self, height: Height) {
+        let mut state = self.state.lock().unwrap();
+        state.height = height.0;
+        self.condvar.notify_all();
+    }
+
+    /// Returns number of requests waiting for new blocks.
+    pub fn waiters(&self) -> usize {
+        self.state.lock().unwrap().waiters
+    }
+
+    /// Blocks until a block above `height` is committed or `timeout` elapses.
+    /// Returns at once if `MAX_WAITERS` requests are already waiting.
+    pub fn wait_above(&self, height: Height, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        if state.waiters >= MAX_WAITERS {
+            return;
+        }
+        state.waiters += 1;
+        while state.height <= height.0 {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            state = self.condvar.wait_timeout(state, deadline - now).unwrap().0;
+        }
+        state.waiters -= 1;
+    }
+}
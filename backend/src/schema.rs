@@ -13,14 +13,21 @@
 // limitations under the License.  
 
 use exonum::{
-    crypto::{Hash, PublicKey}, storage::{Fork, ProofListIndex, ProofMapIndex, Snapshot, MapIndex},
-    messages::{RawMessage},
+    blockchain, crypto::{self, Hash, PublicKey}, helpers::Height,
+    storage::{
+        Entry, Fork, KeySetIndex, ListIndex, MapIndex, ProofListIndex, ProofMapIndex, Snapshot,
+    },
+    messages::{Message, RawMessage, ServiceMessage},
 };
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use unicode_normalization::UnicodeNormalization;
 
+use amount::Amount;
+use events::{EventRecord, PostEvent};
+use transactions::{Cancellation, Error, MailAcceptance};
 use wallet::Wallet;
-use INITIAL_BALANCE;
+use {INITIAL_BALANCE, POST_SERVICE_ID};
 
 
 encoding_struct! {
@@ -35,6 +42,485 @@ encoding_struct! {
     }
 }
 
+encoding_struct! {
+    /// Spending limits of a wallet or a role. Zero means no limit.
+    struct Limits {
+        /// Maximal amount of a single transfer.
+        max_transfer:   u64,
+
+        /// Maximal amount sent from the wallet during a day.
+        daily_outbound: u64,
+
+        /// Maximal amount issued by the issuer during a day.
+        daily_issue:    u64,
+    }
+}
+
+encoding_struct! {
+    /// Transfer reserved by `ScheduledTransfer` and waiting for its execution time.
+    struct PendingTransfer {
+        /// Hash of the `ScheduledTransfer` transaction.
+        tx_hash:    &Hash,
+
+        /// Sender of the transfer.
+        from:       &PublicKey,
+
+        /// Receiver of the transfer.
+        to:         &PublicKey,
+
+        /// Reserved amount.
+        amount:     Amount,
+
+        /// Time at or after which the transfer is executed.
+        execute_at: DateTime<Utc>,
+    }
+}
+
+encoding_struct! {
+    /// Standing order registered by `CreateStandingOrder`.
+    struct StandingOrder {
+        /// Hash of the `CreateStandingOrder` transaction.
+        tx_hash:      &Hash,
+
+        /// Wallet paying under the order.
+        payer:        &PublicKey,
+
+        /// Wallet receiving the payments.
+        payee:        &PublicKey,
+
+        /// Amount of a single payment.
+        amount:       Amount,
+
+        /// Period between payments in seconds.
+        period:       u64,
+
+        /// Time of the next payment.
+        next_payment: DateTime<Utc>,
+
+        /// Time after which no more payments are made.
+        end:          DateTime<Utc>,
+
+        /// Number of payments made so far.
+        payments:     u64,
+
+        /// Number of payments skipped because they could not be made.
+        missed:       u64,
+    }
+}
+
+impl StandingOrder {
+    /// Returns a copy of this order with the next payment moved to the following period.
+    pub fn advance(self, paid: bool) -> Self {
+        let (payments, missed) = if paid {
+            (self.payments() + 1, self.missed())
+        } else {
+            (self.payments(), self.missed() + 1)
+        };
+        Self::new(
+            self.tx_hash(),
+            self.payer(),
+            self.payee(),
+            self.amount(),
+            self.period(),
+            self.next_payment() + Duration::seconds(self.period() as i64),
+            self.end(),
+            payments,
+            missed,
+        )
+    }
+
+    /// Checks whether the order has no more payments to make.
+    pub fn is_finished(&self) -> bool {
+        self.next_payment() > self.end()
+    }
+}
+
+encoding_struct! {
+    /// Payment made under a standing order. Hash of the payment is the synthetic
+    /// history entry of both the payer and the payee.
+    struct StandingOrderPayment {
+        /// Hash of the standing order.
+        order:   &Hash,
+
+        /// Hash of the `ExecuteStandingOrder` transaction that made the payment.
+        tx_hash: &Hash,
+
+        /// Wallet that paid.
+        payer:   &PublicKey,
+
+        /// Wallet that received the payment.
+        payee:   &PublicKey,
+
+        /// Paid amount.
+        amount:  Amount,
+
+        /// Sequence number of the payment within the order.
+        number:  u64,
+    }
+}
+
+encoding_struct! {
+    /// Invoice issued by `CreateInvoice`.
+    struct Invoice {
+        /// Hash of the `CreateInvoice` transaction.
+        tx_hash:    &Hash,
+
+        /// Wallet requesting the payment.
+        payee:      &PublicKey,
+
+        /// Wallet requested to pay.
+        payer:      &PublicKey,
+
+        /// Requested amount.
+        amount:     Amount,
+
+        /// Reference of the invoice in the payee's accounting.
+        reference:  &str,
+
+        /// Time after which the invoice cannot be paid.
+        expires_at: DateTime<Utc>,
+
+        /// Whether the invoice is paid.
+        paid:       bool,
+    }
+}
+
+impl Invoice {
+    /// Returns a copy of this invoice marked as paid.
+    pub fn set_paid(self) -> Self {
+        Self::new(
+            self.tx_hash(),
+            self.payee(),
+            self.payer(),
+            self.amount(),
+            self.reference(),
+            self.expires_at(),
+            true,
+        )
+    }
+}
+
+encoding_struct! {
+    /// Entry of the wallet name directory.
+    struct NameRecord {
+        /// Normalized name.
+        name:    &str,
+
+        /// Public key of the wallet owning the name.
+        pub_key: &PublicKey,
+    }
+}
+
+encoding_struct! {
+    /// Balances of a wallet after a history entry.
+    struct BalanceRecord {
+        /// Available balance.
+        balance:         Amount,
+
+        /// Frozen balance.
+        freezed_balance: Amount,
+    }
+}
+
+encoding_struct! {
+    /// Timestamped transaction or history entry recorded in the time index.
+    struct TimeIndexEntry {
+        /// Hash of the entry.
+        tx_hash: &Hash,
+
+        /// Height of the block the entry was committed in.
+        height:  u64,
+
+        /// Time of the entry.
+        time:    DateTime<Utc>,
+    }
+}
+
+encoding_struct! {
+    /// Asset registered by `CreateAsset` or `CreateVoucher`.
+    ///
+    /// Postage units are the built-in asset with the zero identifier; they are kept
+    /// in the wallet balance and have no registry entry.
+    struct Asset {
+        /// Identifier of the asset, the hash of the registering transaction.
+        id:     &Hash,
+
+        /// Name of the asset.
+        name:   &str,
+
+        /// Issuer allowed to issue the asset.
+        issuer: &PublicKey,
+    }
+}
+
+encoding_struct! {
+    /// Balance of a wallet in a registered asset.
+    struct AssetBalance {
+        /// Public key of the wallet.
+        pub_key: &PublicKey,
+
+        /// Identifier of the asset.
+        asset:   &Hash,
+
+        /// Available balance.
+        balance: Amount,
+    }
+}
+
+encoding_struct! {
+    /// Amount of a registered asset frozen by `AssetMailPreparation`.
+    struct AssetPreparation {
+        /// Identifier of the asset.
+        asset:  &Hash,
+
+        /// Frozen amount.
+        amount: Amount,
+    }
+}
+
+encoding_struct! {
+    /// Terms of a voucher asset registered by `CreateVoucher`.
+    ///
+    /// Vouchers can only be spent on mail and become void after `expires_at`.
+    struct Voucher {
+        /// Identifier of the asset.
+        asset:      &Hash,
+
+        /// Time after which the voucher can no longer be issued or spent.
+        expires_at: DateTime<Utc>,
+    }
+}
+
+impl Voucher {
+    /// Checks whether the voucher is void at the given time.
+    pub fn is_expired(&self, time: DateTime<Utc>) -> bool {
+        time > self.expires_at()
+    }
+}
+
+encoding_struct! {
+    /// Individually numbered digital stamp minted by `MintStamps`.
+    struct Stamp {
+        /// Identifier of the stamp, see `stamp_id`.
+        id:           &Hash,
+
+        /// Series the stamp belongs to.
+        series:       &str,
+
+        /// Serial number of the stamp within the series.
+        number:       u64,
+
+        /// Face value of the stamp in postage units.
+        denomination: Amount,
+
+        /// Hash of the stamp artwork.
+        artwork:      &Hash,
+
+        /// Issuer that minted the stamp.
+        issuer:       &PublicKey,
+
+        /// Wallet owning the stamp.
+        owner:        &PublicKey,
+
+        /// Hash of the `StampMailPreparation` that used the stamp, or zero if it is unused.
+        mail_item:    &Hash,
+
+        /// Hash of the `MailAcceptance` that accepted `mail_item`, or zero if it is not
+        /// accepted.
+        acceptance:   &Hash,
+    }
+}
+
+impl Stamp {
+    /// Checks whether the stamp is used for a mail item.
+    pub fn is_used(&self) -> bool {
+        *self.mail_item() != Hash::zero()
+    }
+
+    /// Checks whether the mail item paid with the stamp was accepted by an inspector.
+    pub fn is_accepted(&self) -> bool {
+        self.is_used() && *self.acceptance() != Hash::zero()
+    }
+
+    /// Returns a copy of this stamp owned by `owner`.
+    pub fn set_owner(self, owner: &PublicKey) -> Self {
+        Self::new(
+            self.id(),
+            self.series(),
+            self.number(),
+            self.denomination(),
+            self.artwork(),
+            self.issuer(),
+            owner,
+            self.mail_item(),
+            self.acceptance(),
+        )
+    }
+
+    /// Returns a copy of this stamp used for `mail_item`, or unused if it is zero.
+    ///
+    /// The acceptance of the previous mail item is cleared.
+    pub fn set_mail_item(self, mail_item: &Hash) -> Self {
+        Self::new(
+            self.id(),
+            self.series(),
+            self.number(),
+            self.denomination(),
+            self.artwork(),
+            self.issuer(),
+            self.owner(),
+            mail_item,
+            &Hash::zero(),
+        )
+    }
+
+    /// Returns a copy of this stamp whose mail item was accepted by `acceptance`.
+    pub fn set_acceptance(self, acceptance: &Hash) -> Self {
+        Self::new(
+            self.id(),
+            self.series(),
+            self.number(),
+            self.denomination(),
+            self.artwork(),
+            self.issuer(),
+            self.owner(),
+            self.mail_item(),
+            acceptance,
+        )
+    }
+}
+
+encoding_struct! {
+    /// Redemption of a stamp by a sorting machine.
+    struct Redemption {
+        /// Identifier of the stamp.
+        stamp:     &Hash,
+
+        /// Hash of the mail item the stamp was used for.
+        mail_item: &Hash,
+
+        /// Key of the sorting machine that redeemed the stamp.
+        machine:   &PublicKey,
+
+        /// Time of the redemption.
+        time:      DateTime<Utc>,
+    }
+}
+
+/// Returns identifier of the stamp with the given serial number in the series of the issuer.
+/// Series of different issuers never share identifiers, so an issuer cannot mint stamps
+/// of another issuer's series.
+pub fn stamp_id(issuer: &PublicKey, series: &str, number: u64) -> Hash {
+    let series = crypto::hash(series.as_bytes());
+    crypto::hash(&[issuer.as_ref(), series.as_ref(), number.to_string().as_bytes()].concat())
+}
+
+/// Returns key of the balance of the wallet in the asset in the `asset_balances` table.
+pub fn asset_balance_key(pub_key: &PublicKey, asset: &Hash) -> Hash {
+    crypto::hash(&[pub_key.as_ref(), asset.as_ref()].concat())
+}
+
+/// Returns key of the balances of the wallet after its history entry at `index`
+/// in the `balance_records` table.
+pub fn balance_record_key(pub_key: &PublicKey, index: u64) -> Hash {
+    crypto::hash(&[pub_key.as_ref(), index.to_string().as_bytes()].concat())
+}
+
+/// Status of a mail item waiting for acceptance, see `CurrencySchema::mail_statuses`.
+pub const MAIL_PENDING: u64 = 0;
+
+/// Status of a mail item accepted by an inspector.
+pub const MAIL_ACCEPTED: u64 = 1;
+
+/// Status of a mail item rejected by an inspector.
+pub const MAIL_REJECTED: u64 = 2;
+
+/// Status of a mail item expired by `ExpirePreparation`.
+pub const MAIL_EXPIRED: u64 = 3;
+
+/// Status of a mail item whose preparation was cancelled by an inspector.
+pub const MAIL_CANCELLED: u64 = 4;
+
+/// Maximal length of a registered name.
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Maximal number of names registered to a wallet.
+pub const MAX_WALLET_NAMES: usize = 16;
+
+/// Scripts that may not be mixed in a name, because their letters look alike.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Other,
+}
+
+/// Returns script of the letter, or `None` if `c` is not a letter.
+fn script(c: char) -> Option<Script> {
+    if !c.is_alphabetic() {
+        return None;
+    }
+    let script = match c as u32 {
+        0x0000..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => Script::Greek,
+        0x0400..=0x052F | 0x1C80..=0x1C8F | 0x2DE0..=0x2DFF | 0xA640..=0xA69F => {
+            Script::Cyrillic
+        }
+        _ => Script::Other,
+    };
+    Some(script)
+}
+
+/// Returns normalized form of the name used as the directory key,
+/// or `None` if the name is not valid.
+///
+/// The name is brought to the NFKC form and lowercased. Letters of a valid name belong
+/// to a single script, so that names like `аlice` with a Cyrillic `а` are rejected.
+pub fn normalize_name(name: &str) -> Option<String> {
+    let name = name.nfkc().collect::<String>().to_lowercase();
+    let name = name.trim();
+    let mut scripts = name.chars().filter_map(script);
+    let single_script = match scripts.next() {
+        Some(first) => scripts.all(|script| script == first),
+        None => true,
+    };
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_NAME_LEN
+        && single_script
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if valid {
+        Some(name.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Returns number of the hour the given time belongs to, counting from the Unix epoch.
+/// Times before the epoch belong to the first hour.
+pub fn hour_bucket(time: DateTime<Utc>) -> u64 {
+    (time.timestamp().max(0) / 3600) as u64
+}
+
+/// Returns key of the entries of the post office within the hour
+/// in the `office_time_index` family.
+pub fn office_hour_key(office: &PublicKey, hour: u64) -> Hash {
+    crypto::hash(&[office.as_ref(), hour.to_string().as_bytes()].concat())
+}
+
+/// Returns number of the day the given time belongs to, counting from the Unix epoch.
+pub fn day_bucket(time: DateTime<Utc>) -> u64 {
+    (time.timestamp() / 86_400) as u64
+}
+
+/// Returns key of the given time in the `due_times` index. Times before the Unix epoch
+/// are due immediately.
+fn due_time(time: DateTime<Utc>) -> u64 {
+    time.timestamp().max(0) as u64
+}
+
 
 
 /// Database schema for the cryptocurrency.
@@ -68,14 +554,265 @@ where
         ProofListIndex::new_in_family("cryptocurrency.wallet_history", public_key, &self.view)
     }
 
+    /// Returns `MerklePatriciaTable` with balances of wallets after entries of their
+    /// history, keyed by `balance_record_key`. Entries made before balances were recorded
+    /// have no records, except the last one, which is recorded on the next change of
+    /// the wallet.
+    pub fn balance_records(&self) -> ProofMapIndex<&T, Hash, BalanceRecord> {
+        ProofMapIndex::new("cryptocurrency.history_balances", &self.view)
+    }
+
+    /// Returns balances of the wallet after its history entry at `index`, if recorded.
+    pub fn wallet_balance(&self, public_key: &PublicKey, index: u64) -> Option<BalanceRecord> {
+        self.balance_records().get(&balance_record_key(public_key, index))
+    }
+
     /// Returns wallet for the given public key.
     pub fn wallet(&self, pub_key: &PublicKey) -> Option<Wallet> {
         self.wallets().get(pub_key)
     }
 
+    /// Returns `MerklePatriciaTable` with forwarding records left by key rotations.
+    pub fn key_forwards(&self) -> ProofMapIndex<&T, PublicKey, PublicKey> {
+        ProofMapIndex::new("cryptocurrency.key_forwards", &self.view)
+    }
+
+    /// Returns wallet for the given public key, following forwarding records
+    /// left by key rotations.
+    pub fn forwarded_wallet(&self, pub_key: &PublicKey) -> Option<Wallet> {
+        let forwards = self.key_forwards();
+        let mut key = *pub_key;
+        while let Some(next) = forwards.get(&key) {
+            key = next;
+        }
+        self.wallet(&key)
+    }
+
+    /// Returns `MerklePatriciaTable` with the final state of closed wallets.
+    pub fn closed_wallets(&self) -> ProofMapIndex<&T, PublicKey, Wallet> {
+        ProofMapIndex::new("cryptocurrency.closed_wallets", &self.view)
+    }
+
+    /// Checks whether the key belongs to a wallet, has been rotated away or closed.
+    pub fn is_key_used(&self, pub_key: &PublicKey) -> bool {
+        self.wallets().contains(pub_key)
+            || self.key_forwards().contains(pub_key)
+            || self.closed_wallets().contains(pub_key)
+    }
+
     /// Returns state hash of service database.
     pub fn state_hash(&self) -> Vec<Hash> {
-        vec![self.wallets().merkle_root()]
+        vec![
+            self.wallets().merkle_root(),
+            self.key_forwards().merkle_root(),
+            self.closed_wallets().merkle_root(),
+            self.scheduled_transfers().merkle_root(),
+            self.standing_orders().merkle_root(),
+            self.standing_order_payments().merkle_root(),
+            self.invoices().merkle_root(),
+            self.names().merkle_root(),
+            self.events().merkle_root(),
+            self.assets().merkle_root(),
+            self.asset_balances().merkle_root(),
+            self.vouchers().merkle_root(),
+            self.stamps().merkle_root(),
+            self.redemptions().merkle_root(),
+            self.balance_records().merkle_root(),
+            self.mail_statuses().merkle_root(),
+        ]
+    }
+
+    /// Returns `MerklePatriciaTable` with redemptions of stamps, keyed by the stamp identifier.
+    pub fn redemptions(&self) -> ProofMapIndex<&T, Hash, Redemption> {
+        ProofMapIndex::new("cryptocurrency.redemptions", &self.view)
+    }
+
+    /// Returns `MerklePatriciaTable` with digital stamps, keyed by `stamp_id`.
+    pub fn stamps(&self) -> ProofMapIndex<&T, Hash, Stamp> {
+        ProofMapIndex::new("cryptocurrency.stamps", &self.view)
+    }
+
+    /// Returns identifiers of the stamps owned by the wallet.
+    pub fn owned_stamps(&self, pub_key: &PublicKey) -> KeySetIndex<&T, Hash> {
+        KeySetIndex::new_in_family("cryptocurrency.owned_stamps", pub_key, &self.view)
+    }
+
+    /// Returns table of stamps used by mail preparations, keyed by the preparation
+    /// transaction hash. Entries are kept after the preparation is finished.
+    pub fn stamp_preparations(&self) -> MapIndex<&T, Hash, Hash> {
+        MapIndex::new("cryptocurrency.stamp_preparations", &self.view)
+    }
+
+    /// Returns set of transactions cancelled by inspectors.
+    pub fn cancelled_transactions(&self) -> KeySetIndex<&T, Hash> {
+        KeySetIndex::new("cryptocurrency.cancelled_transactions", &self.view)
+    }
+
+    /// Returns table of mail preparations accepted by inspectors, keyed by the
+    /// `MailAcceptance` transaction hash.
+    pub fn accepted_preparations(&self) -> MapIndex<&T, Hash, Hash> {
+        MapIndex::new("cryptocurrency.accepted_preparations", &self.view)
+    }
+
+    /// Returns `MerklePatriciaTable` with statuses of successful mail preparations,
+    /// keyed by the preparation transaction hash, see `MAIL_PENDING`.
+    pub fn mail_statuses(&self) -> ProofMapIndex<&T, Hash, u64> {
+        ProofMapIndex::new("cryptocurrency.mail_statuses", &self.view)
+    }
+
+    /// Returns the stamp used by the pending mail preparation of the wallet, if any.
+    pub fn pending_stamp_preparation(&self, pub_key: &PublicKey) -> Option<Stamp> {
+        self.pending_preparations()
+            .get(pub_key)
+            .and_then(|preparation| self.stamp_preparations().get(&preparation))
+            .and_then(|stamp| self.stamps().get(&stamp))
+    }
+
+    /// Returns `MerklePatriciaTable` with terms of voucher assets.
+    pub fn vouchers(&self) -> ProofMapIndex<&T, Hash, Voucher> {
+        ProofMapIndex::new("cryptocurrency.vouchers", &self.view)
+    }
+
+    /// Returns `MerklePatriciaTable` with registered assets.
+    pub fn assets(&self) -> ProofMapIndex<&T, Hash, Asset> {
+        ProofMapIndex::new("cryptocurrency.assets", &self.view)
+    }
+
+    /// Returns `MerklePatriciaTable` with balances of wallets in registered assets,
+    /// keyed by `asset_balance_key`. Zero balances are not stored.
+    pub fn asset_balances(&self) -> ProofMapIndex<&T, Hash, AssetBalance> {
+        ProofMapIndex::new("cryptocurrency.asset_balances", &self.view)
+    }
+
+    /// Returns balance of the wallet in the registered asset.
+    pub fn asset_balance(&self, pub_key: &PublicKey, asset: &Hash) -> Amount {
+        self.asset_balances()
+            .get(&asset_balance_key(pub_key, asset))
+            .map_or(Amount(0), |balance| balance.balance())
+    }
+
+    /// Returns identifiers of registered assets in which the wallet has a non-zero balance.
+    pub fn wallet_assets(&self, pub_key: &PublicKey) -> KeySetIndex<&T, Hash> {
+        KeySetIndex::new_in_family("cryptocurrency.wallet_assets", pub_key, &self.view)
+    }
+
+    /// Returns non-zero balances of the wallet in registered assets.
+    pub fn wallet_asset_balances(&self, pub_key: &PublicKey) -> Vec<AssetBalance> {
+        let balances = self.asset_balances();
+        self.wallet_assets(pub_key)
+            .iter()
+            .filter_map(|asset| balances.get(&asset_balance_key(pub_key, &asset)))
+            .collect()
+    }
+
+    /// Returns table of registered asset amounts frozen by mail preparations,
+    /// keyed by the preparation transaction hash.
+    pub fn asset_preparations(&self) -> MapIndex<&T, Hash, AssetPreparation> {
+        MapIndex::new("cryptocurrency.asset_preparations", &self.view)
+    }
+
+    /// Returns pending mail preparation of the wallet in a registered asset, if any.
+    pub fn pending_asset_preparation(&self, pub_key: &PublicKey) -> Option<AssetPreparation> {
+        self.pending_preparations()
+            .get(pub_key)
+            .and_then(|preparation| self.asset_preparations().get(&preparation))
+    }
+
+    /// Returns the log of events emitted by transactions, in execution order.
+    pub fn events(&self) -> ProofListIndex<&T, EventRecord> {
+        ProofListIndex::new("cryptocurrency.events", &self.view)
+    }
+
+    /// Returns `MerklePatriciaTable` with transfers waiting for their execution time.
+    pub fn scheduled_transfers(&self) -> ProofMapIndex<&T, Hash, PendingTransfer> {
+        ProofMapIndex::new("cryptocurrency.scheduled_transfers", &self.view)
+    }
+
+    /// Returns hashes of pending scheduled transfers of the sender with the given public key.
+    pub fn sender_scheduled_transfers(&self, public_key: &PublicKey) -> KeySetIndex<&T, Hash> {
+        KeySetIndex::new_in_family(
+            "cryptocurrency.sender_scheduled_transfers",
+            public_key,
+            &self.view,
+        )
+    }
+
+    /// Returns times in seconds since the Unix epoch at which scheduled transfers or
+    /// standing order payments are due, in ascending order.
+    pub fn due_times(&self) -> KeySetIndex<&T, u64> {
+        KeySetIndex::new("cryptocurrency.due_times", &self.view)
+    }
+
+    /// Returns hashes of scheduled transfers and standing orders due at the given time,
+    /// see `due_times`.
+    pub fn due_schedules(&self, time: u64) -> KeySetIndex<&T, Hash> {
+        KeySetIndex::new_in_family("cryptocurrency.due_schedules", &time, &self.view)
+    }
+
+    /// Returns hashes of scheduled transfers and standing orders due at or before `time`.
+    pub fn due_until(&self, time: DateTime<Utc>) -> Vec<Hash> {
+        let time = due_time(time);
+        self.due_times()
+            .iter()
+            .take_while(|due| *due <= time)
+            .flat_map(|due| self.due_schedules(due).iter().collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Returns `MerklePatriciaTable` with active standing orders.
+    pub fn standing_orders(&self) -> ProofMapIndex<&T, Hash, StandingOrder> {
+        ProofMapIndex::new("cryptocurrency.standing_orders", &self.view)
+    }
+
+    /// Returns `MerklePatriciaTable` with payments made under standing orders.
+    pub fn standing_order_payments(&self) -> ProofMapIndex<&T, Hash, StandingOrderPayment> {
+        ProofMapIndex::new("cryptocurrency.standing_order_payments", &self.view)
+    }
+
+    /// Returns `MerklePatriciaTable` with invoices.
+    pub fn invoices(&self) -> ProofMapIndex<&T, Hash, Invoice> {
+        ProofMapIndex::new("cryptocurrency.invoices", &self.view)
+    }
+
+    /// Returns hashes of invoices addressed to the payer with the given public key.
+    pub fn payer_invoices(&self, public_key: &PublicKey) -> ListIndex<&T, Hash> {
+        ListIndex::new_in_family("cryptocurrency.payer_invoices", public_key, &self.view)
+    }
+
+    /// Returns hashes of invoices issued by the payee with the given public key.
+    pub fn payee_invoices(&self, public_key: &PublicKey) -> ListIndex<&T, Hash> {
+        ListIndex::new_in_family("cryptocurrency.payee_invoices", public_key, &self.view)
+    }
+
+    /// Returns hashes of transfers made with the given reference.
+    pub fn transfers_by_reference(&self, reference: &str) -> ListIndex<&T, Hash> {
+        let reference_hash = crypto::hash(reference.as_bytes());
+        ListIndex::new_in_family(
+            "cryptocurrency.transfers_by_reference",
+            &reference_hash,
+            &self.view,
+        )
+    }
+
+    /// Returns `MerklePatriciaTable` with the wallet name directory, keyed by hashes
+    /// of normalized names.
+    pub fn names(&self) -> ProofMapIndex<&T, Hash, NameRecord> {
+        ProofMapIndex::new("cryptocurrency.names", &self.view)
+    }
+
+    /// Returns directory entry for the given normalized name.
+    pub fn name_record(&self, name: &str) -> Option<NameRecord> {
+        self.names().get(&crypto::hash(name.as_bytes()))
+    }
+
+    /// Returns hashes of normalized names registered by the wallet with the given public key.
+    pub fn wallet_names(&self, public_key: &PublicKey) -> KeySetIndex<&T, Hash> {
+        KeySetIndex::new_in_family("cryptocurrency.wallet_names", public_key, &self.view)
+    }
+
+    /// Returns table of mail preparations waiting for acceptance, keyed by the sender.
+    pub fn pending_preparations(&self) -> MapIndex<&T, PublicKey, Hash> {
+        MapIndex::new("cryptocurrency.pending_preparations", &self.view)
     }
 
     /// Returns table that represents a map from transaction hash into raw transaction message.
@@ -100,10 +837,135 @@ where
     pub fn issuers(&self) -> MapIndex<&T, PublicKey, u64>{
         MapIndex::new("cryptocurrency.issuers_pubkey", &self.view)
     }
+
+    /// Returns table of compliance officers allowed to suspend wallets.
+    pub fn compliance_officers(&self) -> MapIndex<&T, PublicKey, u64> {
+        MapIndex::new("cryptocurrency.compliance_officers_pubkey", &self.view)
+    }
+
+    /// Returns table of sorting machines allowed to redeem stamps.
+    pub fn sorting_machines(&self) -> MapIndex<&T, PublicKey, u64> {
+        MapIndex::new("cryptocurrency.sorting_machines_pubkey", &self.view)
+    }
+
+    /// Returns role of the wallet with the given public key, see `CreateWallet`.
+    pub fn wallet_role(&self, pub_key: &PublicKey) -> u64 {
+        self.inspectors()
+            .get(pub_key)
+            .or_else(|| self.issuers().get(pub_key))
+            .or_else(|| self.compliance_officers().get(pub_key))
+            .or_else(|| self.sorting_machines().get(pub_key))
+            .unwrap_or(0)
+    }
+
+    /// Returns table of spending limits set for individual wallets.
+    pub fn wallet_limits(&self) -> MapIndex<&T, PublicKey, Limits> {
+        MapIndex::new("cryptocurrency.wallet_limits", &self.view)
+    }
+
+    /// Returns table of spending limits set for wallet roles.
+    pub fn role_limits(&self) -> MapIndex<&T, u64, Limits> {
+        MapIndex::new("cryptocurrency.role_limits", &self.view)
+    }
+
+    /// Returns limits applied to the wallet: its own limits if set, otherwise limits
+    /// of its role.
+    pub fn limits(&self, pub_key: &PublicKey) -> Option<Limits> {
+        self.wallet_limits()
+            .get(pub_key)
+            .or_else(|| self.role_limits().get(&self.wallet_role(pub_key)))
+    }
+
+    /// Returns amounts sent from the wallet per day.
+    pub fn daily_outbound(&self, pub_key: &PublicKey) -> MapIndex<&T, u64, u64> {
+        MapIndex::new_in_family("cryptocurrency.daily_outbound", pub_key, &self.view)
+    }
+
+    /// Returns entries of the post office timestamped within the given hour.
+    /// Entries belong to the post office of the inspector who signed them, see `office_of`.
+    pub fn office_time_index(
+        &self,
+        office: &PublicKey,
+        hour: u64,
+    ) -> ListIndex<&T, TimeIndexEntry> {
+        let key = office_hour_key(office, hour);
+        ListIndex::new_in_family("cryptocurrency.office_time_index", &key, &self.view)
+    }
+
+    /// Returns height of the block starting from which timestamped entries are indexed
+    /// as they are added. Earlier entries are indexed by `IndexTimestamps`.
+    pub fn time_index_start(&self) -> Entry<&T, u64> {
+        Entry::new("cryptocurrency.time_index_start", &self.view)
+    }
+
+    /// Returns the last timestamped entry processed by `IndexTimestamps`.
+    pub fn time_index_cursor(&self) -> Entry<&T, Hash> {
+        Entry::new("cryptocurrency.time_index_cursor", &self.view)
+    }
+
+    /// Returns the post office of the timestamped transaction, that is, the inspector
+    /// who signed a mail acceptance or a cancellation.
+    pub fn office_of(&self, tx_hash: &Hash) -> Option<PublicKey> {
+        let raw = self.transactions().get(tx_hash)?;
+        if raw.service_id() != POST_SERVICE_ID {
+            return None;
+        }
+        match raw.message_type() {
+            MailAcceptance::MESSAGE_ID => {
+                let tx: MailAcceptance = Message::from_raw(raw).ok()?;
+                Some(*tx.pub_key())
+            }
+            Cancellation::MESSAGE_ID => {
+                let tx: Cancellation = Message::from_raw(raw).ok()?;
+                Some(*tx.pub_key())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns height of the block the history entry or the timestamped transaction
+    /// was committed in.
+    pub fn entry_height(&self, record: &Hash) -> Option<Height> {
+        let tx_hash = self
+            .standing_order_payments()
+            .get(record)
+            .map_or(*record, |payment| *payment.tx_hash());
+        blockchain::Schema::new(&self.view)
+            .transactions_locations()
+            .get(&tx_hash)
+            .map(|location| location.block_height())
+    }
+
+    /// Returns entries timestamped within the given hour, see `hour_bucket`.
+    pub fn time_index(&self, hour: u64) -> ListIndex<&T, TimeIndexEntry> {
+        ListIndex::new_in_family("cryptocurrency.time_index", &hour, &self.view)
+    }
+
+    /// Returns amounts issued by the issuer per day.
+    pub fn daily_issued(&self, pub_key: &PublicKey) -> MapIndex<&T, u64, u64> {
+        MapIndex::new_in_family("cryptocurrency.daily_issued", pub_key, &self.view)
+    }
 }
 
 /// Implementation of mutable methods.
 impl<'a> CurrencySchema<&'a mut Fork> {
+    /// Returns mutable log of events.
+    pub fn events_mut(&mut self) -> ProofListIndex<&mut Fork, EventRecord> {
+        ProofListIndex::new("cryptocurrency.events", &mut self.view)
+    }
+
+    /// Returns the height of the block being built.
+    fn block_height(&self) -> u64 {
+        // It is equal to the number of committed blocks.
+        blockchain::Schema::new(&self.view).block_hashes_by_height().len()
+    }
+
+    /// Appends an event emitted by the transaction with `tx_hash` to the event log.
+    pub fn emit_event(&mut self, tx_hash: &Hash, event: PostEvent) {
+        let record = EventRecord::from_event(Height(self.block_height()), tx_hash, &event);
+        self.events_mut().push(record);
+    }
+
     /// Returns mutable `MerklePatriciaTable` with wallets.
     pub fn wallets_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, Wallet> {
         ProofMapIndex::new("cryptocurrency.wallets", &mut self.view)
@@ -117,47 +979,497 @@ impl<'a> CurrencySchema<&'a mut Fork> {
         ProofListIndex::new_in_family("cryptocurrency.wallet_history", public_key, &mut self.view)
     }
 
+    /// Returns mutable `MerklePatriciaTable` with balances of wallets after history entries.
+    pub fn balance_records_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, BalanceRecord> {
+        ProofMapIndex::new("cryptocurrency.history_balances", &mut self.view)
+    }
+
+    /// Records balances of the wallet after its latest history entry.
+    fn record_balance(&mut self, wallet: &Wallet) {
+        let record = BalanceRecord::new(wallet.balance(), wallet.freezed_balance());
+        let key = balance_record_key(wallet.pub_key(), wallet.history_len() - 1);
+        self.balance_records_mut().put(&key, record);
+    }
+
+    /// Records balances of a wallet created before balances were recorded, so that
+    /// statements of such wallets start from a known balance. Called before the wallet
+    /// gets a new history entry.
+    fn backfill_balance(&mut self, wallet: &Wallet) {
+        let index = wallet.history_len() - 1;
+        if self.wallet_balance(wallet.pub_key(), index).is_none() {
+            self.record_balance(wallet);
+        }
+    }
+
     /// Increase balance of the wallet and append new record to its history.
     ///
-    /// Panics if there is no wallet with given public key.
-    pub fn increase_wallet_balance(&mut self, wallet: Wallet, amount: u64, transaction: &Hash, freezed_balance: u64) {
+    /// Fails with `BalanceOverflow` if the balance doesn't fit into `u64`.
+    pub fn increase_wallet_balance(
+        &mut self,
+        wallet: Wallet,
+        amount: Amount,
+        transaction: &Hash,
+        freezed_balance: Amount,
+    ) -> Result<(), Error> {
+        let balance = wallet.balance().checked_add(amount)?;
+        self.set_wallet_balance(wallet, balance, transaction, freezed_balance);
+        Ok(())
+    }
+
+    /// Decrease balance of the wallet and append new record to its history.
+    ///
+    /// Fails with `InsufficientCurrencyAmount` if the balance is less than `amount`.
+    pub fn decrease_wallet_balance(
+        &mut self,
+        wallet: Wallet,
+        amount: Amount,
+        transaction: &Hash,
+        freezed_balance: Amount,
+    ) -> Result<(), Error> {
+        let balance = wallet.balance().checked_sub(amount)?;
+        self.set_wallet_balance(wallet, balance, transaction, freezed_balance);
+        Ok(())
+    }
+
+    /// Append a record that leaves the balances of the wallet unchanged to its history,
+    /// such as a move of a registered asset.
+    pub fn append_history(&mut self, wallet: Wallet, transaction: &Hash) {
+        let (balance, freezed_balance) = (wallet.balance(), wallet.freezed_balance());
+        self.set_wallet_balance(wallet, balance, transaction, freezed_balance);
+    }
+
+    fn set_wallet_balance(
+        &mut self,
+        wallet: Wallet,
+        balance: Amount,
+        transaction: &Hash,
+        freezed_balance: Amount,
+    ) {
+        self.backfill_balance(&wallet);
         let wallet = {
             let mut history = self.wallet_history_mut(wallet.pub_key());
             history.push(*transaction);
             let history_hash = history.merkle_root();
-            let balance = wallet.balance();
-            /////////////////////////////
-            wallet.set_balance(balance + amount, &history_hash, freezed_balance)
+            wallet.set_balance(balance, &history_hash, freezed_balance)
         };
+        self.record_balance(&wallet);
         self.wallets_mut().put(wallet.pub_key(), wallet.clone());
     }
 
-    /// Decrease balance of the wallet and append new record to its history.
+    /// Move the wallet to a new public key and leave a forwarding record at the old one.
     ///
-    /// Panics if there is no wallet with given public key.
-    pub fn decrease_wallet_balance(&mut self, wallet: Wallet, amount: u64, transaction: &Hash, freezed_balance: u64) {
+    /// History, roles, limits and daily volumes, pending scheduled transfers, invoice
+    /// listings and names of the wallet are moved along with its balance.
+    pub fn rotate_wallet_key(&mut self, wallet: Wallet, new_key: &PublicKey, transaction: &Hash) {
+        self.backfill_balance(&wallet);
+        let old_key = *wallet.pub_key();
+        let old_history = self.wallet_history(&old_key).iter().collect::<Vec<_>>();
+        let old_balances = (0..wallet.history_len())
+            .filter_map(|index| Some((index, self.wallet_balance(&old_key, index)?)))
+            .collect::<Vec<_>>();
+        let wallet = {
+            let mut history = self.wallet_history_mut(new_key);
+            history.extend(old_history);
+            history.push(*transaction);
+            let history_hash = history.merkle_root();
+            Wallet::new(
+                new_key,
+                wallet.name(),
+                wallet.balance(),
+                history.len(),
+                &history_hash,
+                wallet.freezed_balance(),
+                wallet.suspended(),
+            )
+        };
+        self.wallet_history_mut(&old_key).push(*transaction);
+        // Rotation moves no funds, so both keys record the unchanged balances.
+        for (index, record) in old_balances {
+            self.balance_records_mut().put(&balance_record_key(new_key, index), record);
+        }
+        self.record_balance(&wallet);
+        let record = BalanceRecord::new(wallet.balance(), wallet.freezed_balance());
+        let key = balance_record_key(&old_key, wallet.history_len() - 1);
+        self.balance_records_mut().put(&key, record);
+        self.wallets_mut().remove(&old_key);
+        self.wallets_mut().put(new_key, wallet);
+        self.key_forwards_mut().put(&old_key, *new_key);
+
+        if let Some(user) = self.inspectors().get(&old_key) {
+            self.inspectors_mut().remove(&old_key);
+            self.inspectors_mut().put(new_key, user);
+        }
+        if let Some(user) = self.issuers().get(&old_key) {
+            self.issuers_mut().remove(&old_key);
+            self.issuers_mut().put(new_key, user);
+        }
+        if let Some(user) = self.compliance_officers().get(&old_key) {
+            self.compliance_officers_mut().remove(&old_key);
+            self.compliance_officers_mut().put(new_key, user);
+        }
+        if let Some(user) = self.sorting_machines().get(&old_key) {
+            self.sorting_machines_mut().remove(&old_key);
+            self.sorting_machines_mut().put(new_key, user);
+        }
+        if let Some(limits) = self.wallet_limits().get(&old_key) {
+            self.wallet_limits_mut().remove(&old_key);
+            self.wallet_limits_mut().put(new_key, limits);
+        }
+        if let Some(preparation) = self.pending_preparations().get(&old_key) {
+            self.pending_preparations_mut().remove(&old_key);
+            self.pending_preparations_mut().put(new_key, preparation);
+        }
+        let scheduled = self.sender_scheduled_transfers(&old_key).iter().collect::<Vec<_>>();
+        self.sender_scheduled_transfers_mut(&old_key).clear();
+        for tx_hash in scheduled {
+            self.sender_scheduled_transfers_mut(new_key).insert(tx_hash);
+        }
+        self.move_daily_volumes("cryptocurrency.daily_outbound", &old_key, new_key);
+        self.move_daily_volumes("cryptocurrency.daily_issued", &old_key, new_key);
+        let payer_invoices = self.payer_invoices(&old_key).iter().collect::<Vec<_>>();
+        self.payer_invoices_mut(&old_key).clear();
+        self.payer_invoices_mut(new_key).extend(payer_invoices);
+        let payee_invoices = self.payee_invoices(&old_key).iter().collect::<Vec<_>>();
+        self.payee_invoices_mut(&old_key).clear();
+        self.payee_invoices_mut(new_key).extend(payee_invoices);
+        let names = self.wallet_names(&old_key).iter().collect::<Vec<_>>();
+        self.wallet_names_mut(&old_key).clear();
+        for name_hash in names {
+            self.wallet_names_mut(new_key).insert(name_hash);
+        }
+    }
+
+    /// Suspend or unsuspend the wallet and append new record to its history.
+    pub fn set_wallet_suspended(&mut self, wallet: Wallet, suspended: bool, transaction: &Hash) {
+        self.backfill_balance(&wallet);
         let wallet = {
             let mut history = self.wallet_history_mut(wallet.pub_key());
             history.push(*transaction);
             let history_hash = history.merkle_root();
-            let balance = wallet.balance();
-            wallet.set_balance(balance - amount, &history_hash, freezed_balance)
+            wallet.set_suspended(suspended, &history_hash)
         };
+        self.record_balance(&wallet);
         self.wallets_mut().put(wallet.pub_key(), wallet.clone());
     }
 
+    /// Close the wallet: append the closing record to its history, zero its balance
+    /// and move it from `wallets` to `closed_wallets` together with its roles removal.
+    pub fn close_wallet(&mut self, wallet: Wallet, transaction: &Hash) {
+        self.backfill_balance(&wallet);
+        let wallet = {
+            let mut history = self.wallet_history_mut(wallet.pub_key());
+            history.push(*transaction);
+            let history_hash = history.merkle_root();
+            wallet.set_balance(Amount(0), &history_hash, Amount(0))
+        };
+        self.record_balance(&wallet);
+        let key = *wallet.pub_key();
+        self.wallets_mut().remove(&key);
+        self.closed_wallets_mut().put(&key, wallet);
+        self.inspectors_mut().remove(&key);
+        self.issuers_mut().remove(&key);
+        self.compliance_officers_mut().remove(&key);
+        self.sorting_machines_mut().remove(&key);
+        self.wallet_limits_mut().remove(&key);
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with registered assets.
+    pub fn assets_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Asset> {
+        ProofMapIndex::new("cryptocurrency.assets", &mut self.view)
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with redemptions of stamps.
+    pub fn redemptions_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Redemption> {
+        ProofMapIndex::new("cryptocurrency.redemptions", &mut self.view)
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with digital stamps.
+    pub fn stamps_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Stamp> {
+        ProofMapIndex::new("cryptocurrency.stamps", &mut self.view)
+    }
+
+    /// Returns mutable identifiers of the stamps owned by the wallet.
+    pub fn owned_stamps_mut(&mut self, pub_key: &PublicKey) -> KeySetIndex<&mut Fork, Hash> {
+        KeySetIndex::new_in_family("cryptocurrency.owned_stamps", pub_key, &mut self.view)
+    }
+
+    /// Returns mutable table of stamps used by mail preparations.
+    pub fn stamp_preparations_mut(&mut self) -> MapIndex<&mut Fork, Hash, Hash> {
+        MapIndex::new("cryptocurrency.stamp_preparations", &mut self.view)
+    }
+
+    /// Returns mutable set of transactions cancelled by inspectors.
+    pub fn cancelled_transactions_mut(&mut self) -> KeySetIndex<&mut Fork, Hash> {
+        KeySetIndex::new("cryptocurrency.cancelled_transactions", &mut self.view)
+    }
+
+    /// Returns mutable table of mail preparations accepted by inspectors.
+    pub fn accepted_preparations_mut(&mut self) -> MapIndex<&mut Fork, Hash, Hash> {
+        MapIndex::new("cryptocurrency.accepted_preparations", &mut self.view)
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with statuses of mail preparations.
+    pub fn mail_statuses_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, u64> {
+        ProofMapIndex::new("cryptocurrency.mail_statuses", &mut self.view)
+    }
+
+    /// Store the stamp with `owner` as its new owner.
+    pub fn set_stamp_owner(&mut self, stamp: Stamp, owner: &PublicKey) {
+        let id = *stamp.id();
+        self.owned_stamps_mut(stamp.owner()).remove(&id);
+        self.owned_stamps_mut(owner).insert(id);
+        self.stamps_mut().put(&id, stamp.set_owner(owner));
+    }
+
+    /// Move all stamps owned by one wallet to another.
+    pub fn move_stamps(&mut self, from: &PublicKey, to: &PublicKey) {
+        let owned = self.owned_stamps(from).iter().collect::<Vec<_>>();
+        for id in owned {
+            if let Some(stamp) = self.stamps().get(&id) {
+                self.set_stamp_owner(stamp, to);
+            }
+        }
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with terms of voucher assets.
+    pub fn vouchers_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Voucher> {
+        ProofMapIndex::new("cryptocurrency.vouchers", &mut self.view)
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with balances in registered assets.
+    pub fn asset_balances_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, AssetBalance> {
+        ProofMapIndex::new("cryptocurrency.asset_balances", &mut self.view)
+    }
+
+    /// Returns mutable identifiers of registered assets held by the wallet.
+    pub fn wallet_assets_mut(&mut self, pub_key: &PublicKey) -> KeySetIndex<&mut Fork, Hash> {
+        KeySetIndex::new_in_family("cryptocurrency.wallet_assets", pub_key, &mut self.view)
+    }
+
+    /// Returns mutable table of registered asset amounts frozen by mail preparations.
+    pub fn asset_preparations_mut(&mut self) -> MapIndex<&mut Fork, Hash, AssetPreparation> {
+        MapIndex::new("cryptocurrency.asset_preparations", &mut self.view)
+    }
+
+    /// Increase balance of the wallet in the registered asset.
+    ///
+    /// Fails with `BalanceOverflow` if the balance doesn't fit into `u64`.
+    pub fn increase_asset_balance(
+        &mut self,
+        pub_key: &PublicKey,
+        asset: &Hash,
+        amount: Amount,
+    ) -> Result<(), Error> {
+        let balance = self.asset_balance(pub_key, asset).checked_add(amount)?;
+        self.set_asset_balance(pub_key, asset, balance);
+        Ok(())
+    }
+
+    /// Decrease balance of the wallet in the registered asset.
+    ///
+    /// Fails with `InsufficientCurrencyAmount` if the balance is less than `amount`.
+    pub fn decrease_asset_balance(
+        &mut self,
+        pub_key: &PublicKey,
+        asset: &Hash,
+        amount: Amount,
+    ) -> Result<(), Error> {
+        let balance = self.asset_balance(pub_key, asset).checked_sub(amount)?;
+        self.set_asset_balance(pub_key, asset, balance);
+        Ok(())
+    }
+
+    fn set_asset_balance(&mut self, pub_key: &PublicKey, asset: &Hash, balance: Amount) {
+        let key = asset_balance_key(pub_key, asset);
+        if balance == Amount(0) {
+            self.asset_balances_mut().remove(&key);
+            self.wallet_assets_mut(pub_key).remove(asset);
+        } else {
+            let record = AssetBalance::new(pub_key, asset, balance);
+            self.asset_balances_mut().put(&key, record);
+            self.wallet_assets_mut(pub_key).insert(*asset);
+        }
+    }
+
+    /// Remove all voucher balances of the wallet and return the removed balances.
+    pub fn void_vouchers(&mut self, pub_key: &PublicKey) -> Result<Vec<AssetBalance>, Error> {
+        let vouchers = self
+            .wallet_asset_balances(pub_key)
+            .into_iter()
+            .filter(|balance| self.vouchers().contains(balance.asset()))
+            .collect::<Vec<_>>();
+        for balance in &vouchers {
+            self.decrease_asset_balance(pub_key, balance.asset(), balance.balance())?;
+        }
+        Ok(vouchers)
+    }
+
+    /// Move all balances in registered assets from one wallet to another.
+    pub fn move_asset_balances(&mut self, from: &PublicKey, to: &PublicKey) -> Result<(), Error> {
+        for balance in self.wallet_asset_balances(from) {
+            let amount = balance.balance();
+            self.decrease_asset_balance(from, balance.asset(), amount)?;
+            self.increase_asset_balance(to, balance.asset(), amount)?;
+        }
+        Ok(())
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with closed wallets.
+    pub fn closed_wallets_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, Wallet> {
+        ProofMapIndex::new("cryptocurrency.closed_wallets", &mut self.view)
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with forwarding records.
+    pub fn key_forwards_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, PublicKey> {
+        ProofMapIndex::new("cryptocurrency.key_forwards", &mut self.view)
+    }
+
     /// Create new wallet and append first record to its history.
-    pub fn create_wallet(&mut self, key: &PublicKey, name: &str, transaction: &Hash, freezed_balance: u64) {
+    pub fn create_wallet(
+        &mut self,
+        key: &PublicKey,
+        name: &str,
+        transaction: &Hash,
+        freezed_balance: Amount,
+    ) {
         let wallet = {
             let mut history = self.wallet_history_mut(key);
             history.push(*transaction);
             let history_hash = history.merkle_root();
-            let freezed_balance = 0;
-            Wallet::new(key, name, INITIAL_BALANCE, history.len(), &history_hash, freezed_balance)
+            let freezed_balance = Amount(0);
+            Wallet::new(
+                key,
+                name,
+                INITIAL_BALANCE,
+                history.len(),
+                &history_hash,
+                freezed_balance,
+                false,
+            )
         };
+        self.record_balance(&wallet);
         self.wallets_mut().put(key, wallet);
     }
 
+    /// Returns mutable `MerklePatriciaTable` with scheduled transfers.
+    pub fn scheduled_transfers_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, PendingTransfer> {
+        ProofMapIndex::new("cryptocurrency.scheduled_transfers", &mut self.view)
+    }
+
+    /// Returns mutable hashes of pending scheduled transfers of the sender.
+    pub fn sender_scheduled_transfers_mut(
+        &mut self,
+        public_key: &PublicKey,
+    ) -> KeySetIndex<&mut Fork, Hash> {
+        KeySetIndex::new_in_family(
+            "cryptocurrency.sender_scheduled_transfers",
+            public_key,
+            &mut self.view,
+        )
+    }
+
+    /// Returns mutable times at which schedules are due.
+    pub fn due_times_mut(&mut self) -> KeySetIndex<&mut Fork, u64> {
+        KeySetIndex::new("cryptocurrency.due_times", &mut self.view)
+    }
+
+    /// Returns mutable hashes of schedules due at the given time.
+    pub fn due_schedules_mut(&mut self, time: u64) -> KeySetIndex<&mut Fork, Hash> {
+        KeySetIndex::new_in_family("cryptocurrency.due_schedules", &time, &mut self.view)
+    }
+
+    /// Add the scheduled transfer or standing order with `tx_hash` to the schedules
+    /// due at `time`.
+    pub fn add_due(&mut self, time: DateTime<Utc>, tx_hash: &Hash) {
+        let time = due_time(time);
+        self.due_schedules_mut(time).insert(*tx_hash);
+        self.due_times_mut().insert(time);
+    }
+
+    /// Remove the scheduled transfer or standing order with `tx_hash` from the schedules
+    /// due at `time`.
+    pub fn remove_due(&mut self, time: DateTime<Utc>, tx_hash: &Hash) {
+        let time = due_time(time);
+        self.due_schedules_mut(time).remove(tx_hash);
+        if self.due_schedules(time).iter().next().is_none() {
+            self.due_times_mut().remove(&time);
+        }
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with standing orders.
+    pub fn standing_orders_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, StandingOrder> {
+        ProofMapIndex::new("cryptocurrency.standing_orders", &mut self.view)
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with standing order payments.
+    pub fn standing_order_payments_mut(
+        &mut self,
+    ) -> ProofMapIndex<&mut Fork, Hash, StandingOrderPayment> {
+        ProofMapIndex::new("cryptocurrency.standing_order_payments", &mut self.view)
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with invoices.
+    pub fn invoices_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Invoice> {
+        ProofMapIndex::new("cryptocurrency.invoices", &mut self.view)
+    }
+
+    /// Returns mutable hashes of invoices addressed to the payer.
+    pub fn payer_invoices_mut(&mut self, public_key: &PublicKey) -> ListIndex<&mut Fork, Hash> {
+        ListIndex::new_in_family("cryptocurrency.payer_invoices", public_key, &mut self.view)
+    }
+
+    /// Returns mutable hashes of invoices issued by the payee.
+    pub fn payee_invoices_mut(&mut self, public_key: &PublicKey) -> ListIndex<&mut Fork, Hash> {
+        ListIndex::new_in_family("cryptocurrency.payee_invoices", public_key, &mut self.view)
+    }
+
+    /// Add new invoice and index it by its payer and payee.
+    pub fn add_invoice(&mut self, invoice: Invoice) {
+        let hash = *invoice.tx_hash();
+        self.payer_invoices_mut(invoice.payer()).push(hash);
+        self.payee_invoices_mut(invoice.payee()).push(hash);
+        self.invoices_mut().put(&hash, invoice);
+    }
+
+    /// Returns mutable hashes of transfers made with the given reference.
+    pub fn transfers_by_reference_mut(&mut self, reference: &str) -> ListIndex<&mut Fork, Hash> {
+        let reference_hash = crypto::hash(reference.as_bytes());
+        ListIndex::new_in_family(
+            "cryptocurrency.transfers_by_reference",
+            &reference_hash,
+            &mut self.view,
+        )
+    }
+
+    /// Returns mutable `MerklePatriciaTable` with the wallet name directory.
+    pub fn names_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, NameRecord> {
+        ProofMapIndex::new("cryptocurrency.names", &mut self.view)
+    }
+
+    /// Returns mutable hashes of names registered by the wallet.
+    pub fn wallet_names_mut(&mut self, public_key: &PublicKey) -> KeySetIndex<&mut Fork, Hash> {
+        KeySetIndex::new_in_family("cryptocurrency.wallet_names", public_key, &mut self.view)
+    }
+
+    /// Release all names registered by the wallet and return them.
+    pub fn release_names(&mut self, pub_key: &PublicKey) -> Vec<String> {
+        let hashes = self.wallet_names(pub_key).iter().collect::<Vec<_>>();
+        let mut released = Vec::new();
+        for name_hash in hashes {
+            if let Some(record) = self.names().get(&name_hash) {
+                self.names_mut().remove(&name_hash);
+                released.push(record.name().to_owned());
+            }
+        }
+        self.wallet_names_mut(pub_key).clear();
+        released
+    }
+
+    /// Returns mutable table of mail preparations waiting for acceptance.
+    pub fn pending_preparations_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, Hash> {
+        MapIndex::new("cryptocurrency.pending_preparations", &mut self.view)
+    }
+
     /// Returns mut table that represents a map from transaction hash into raw transaction message.
     pub fn transactions_mut(&mut self) -> MapIndex<&mut Fork, Hash, RawMessage> {
         MapIndex::new("core.transactions", &mut self.view)
@@ -179,12 +1491,93 @@ impl<'a> CurrencySchema<&'a mut Fork> {
         }
         // Add timestamp
         self.timestamps_mut().put(tx_hash, time.timestamp());
+
+        self.start_time_index();
+        let entry = TimeIndexEntry::new(tx_hash, self.block_height(), time);
+        self.index_time(entry);
+    }
+
+    /// Returns mutable entries timestamped within the given hour.
+    pub fn time_index_mut(&mut self, hour: u64) -> ListIndex<&mut Fork, TimeIndexEntry> {
+        ListIndex::new_in_family("cryptocurrency.time_index", &hour, &mut self.view)
+    }
+
+    /// Returns mutable entries of the post office timestamped within the given hour.
+    pub fn office_time_index_mut(
+        &mut self,
+        office: &PublicKey,
+        hour: u64,
+    ) -> ListIndex<&mut Fork, TimeIndexEntry> {
+        let key = office_hour_key(office, hour);
+        ListIndex::new_in_family("cryptocurrency.office_time_index", &key, &mut self.view)
+    }
+
+    /// Returns mutable height of the block the time index started at.
+    pub fn time_index_start_mut(&mut self) -> Entry<&mut Fork, u64> {
+        Entry::new("cryptocurrency.time_index_start", &mut self.view)
+    }
+
+    /// Returns mutable last timestamped entry processed by `IndexTimestamps`.
+    pub fn time_index_cursor_mut(&mut self) -> Entry<&mut Fork, Hash> {
+        Entry::new("cryptocurrency.time_index_cursor", &mut self.view)
+    }
+
+    /// Returns height of the block the time index started at, starting it at the current
+    /// block if needed.
+    fn start_time_index(&mut self) -> u64 {
+        if let Some(start) = self.time_index_start().get() {
+            return start;
+        }
+        let start = self.block_height();
+        self.time_index_start_mut().set(start);
+        start
+    }
+
+    /// Adds the entry to the time index and to the index of its post office.
+    fn index_time(&mut self, entry: TimeIndexEntry) {
+        let hour = hour_bucket(entry.time());
+        if let Some(office) = self.office_of(entry.tx_hash()) {
+            self.office_time_index_mut(&office, hour).push(entry.clone());
+        }
+        self.time_index_mut(hour).push(entry);
+    }
+
+    /// Indexes entries timestamped before the time index started, processing up to `count`
+    /// timestamps after the last processed one. Returns number of processed timestamps.
+    pub fn backfill_time_index(&mut self, count: u64) -> u64 {
+        let start = self.start_time_index();
+        let cursor = self.time_index_cursor().get();
+        let batch = {
+            let timestamps = self.timestamps();
+            let iter = match cursor {
+                Some(ref cursor) => timestamps.iter_from(cursor),
+                None => timestamps.iter(),
+            };
+            iter.filter(|(tx_hash, _)| Some(*tx_hash) != cursor)
+                .take(count as usize)
+                .collect::<Vec<_>>()
+        };
+
+        for (tx_hash, seconds) in &batch {
+            match self.entry_height(tx_hash) {
+                Some(height) if height.0 < start => {
+                    let entry = TimeIndexEntry::new(tx_hash, height.0, Utc.timestamp(*seconds, 0));
+                    self.index_time(entry);
+                }
+                _ => {}
+            }
+        }
+        if let Some((tx_hash, _)) = batch.last() {
+            self.time_index_cursor_mut().set(*tx_hash);
+        }
+        batch.len() as u64
     }
+
     pub fn inspectors_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, u64> {
         MapIndex::new("cryptocurrency.inspectors_pubkey", &mut self.view)
     }
     pub fn add_inspector(&mut self, pub_key: &PublicKey, user: u64) {
-        if self.inspectors().contains(pub_key) || user == 0 || user == 2{
+        if self.inspectors().contains(pub_key) || user != 1 {
             return;
         }
 
@@ -196,10 +1589,79 @@ impl<'a> CurrencySchema<&'a mut Fork> {
     }
 
     pub fn add_issuer(&mut self, pub_key: &PublicKey, user: u64) {
-        if self.issuers().contains(pub_key) || user == 0 || user == 1{
+        if self.issuers().contains(pub_key) || user != 2 {
             return;
         }
 
         self.issuers_mut().put(&pub_key, user);
     }
+
+    pub fn compliance_officers_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, u64> {
+        MapIndex::new("cryptocurrency.compliance_officers_pubkey", &mut self.view)
+    }
+
+    pub fn add_compliance_officer(&mut self, pub_key: &PublicKey, user: u64) {
+        if self.compliance_officers().contains(pub_key) || user != 3 {
+            return;
+        }
+
+        self.compliance_officers_mut().put(&pub_key, user);
+    }
+
+    pub fn sorting_machines_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, u64> {
+        MapIndex::new("cryptocurrency.sorting_machines_pubkey", &mut self.view)
+    }
+
+    pub fn add_sorting_machine(&mut self, pub_key: &PublicKey, user: u64) {
+        if self.sorting_machines().contains(pub_key) || user != 4 {
+            return;
+        }
+
+        self.sorting_machines_mut().put(&pub_key, user);
+    }
+
+    pub fn wallet_limits_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, Limits> {
+        MapIndex::new("cryptocurrency.wallet_limits", &mut self.view)
+    }
+
+    pub fn role_limits_mut(&mut self) -> MapIndex<&mut Fork, u64, Limits> {
+        MapIndex::new("cryptocurrency.role_limits", &mut self.view)
+    }
+
+    /// Adds `amount` to the volume sent from the wallet on the given `day`.
+    pub fn add_daily_outbound(&mut self, pub_key: &PublicKey, day: u64, amount: Amount) {
+        let mut index: MapIndex<_, u64, u64> = MapIndex::new_in_family(
+            "cryptocurrency.daily_outbound",
+            pub_key,
+            &mut self.view,
+        );
+        let volume = index.get(&day).unwrap_or(0);
+        index.put(&day, volume.saturating_add(amount.0));
+    }
+
+    /// Moves volumes counted per day in the `family` of indices from one key to another.
+    fn move_daily_volumes(&mut self, family: &str, from: &PublicKey, to: &PublicKey) {
+        let volumes = {
+            let mut index: MapIndex<_, u64, u64> =
+                MapIndex::new_in_family(family, from, &mut self.view);
+            let volumes = index.iter().collect::<Vec<_>>();
+            index.clear();
+            volumes
+        };
+        let mut index: MapIndex<_, u64, u64> = MapIndex::new_in_family(family, to, &mut self.view);
+        for (day, volume) in volumes {
+            index.put(&day, volume);
+        }
+    }
+
+    /// Adds `amount` to the volume issued by the issuer on the given `day`.
+    pub fn add_daily_issued(&mut self, pub_key: &PublicKey, day: u64, amount: Amount) {
+        let mut index: MapIndex<_, u64, u64> = MapIndex::new_in_family(
+            "cryptocurrency.daily_issued",
+            pub_key,
+            &mut self.view,
+        );
+        let volume = index.get(&day).unwrap_or(0);
+        index.put(&day, volume.saturating_add(amount.0));
+    }
 }
@@ -0,0 +1,414 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compact postage tokens for offline verification.
+//!
+//! A token holds the signed mail preparation transaction together with the latest
+//! block at the issue of the token, the precommits of the block and the proof of the
+//! mail item status in the state hash of the block. Couriers can check a token without
+//! connectivity against a cached set of validator consensus keys and read the sender
+//! and the postage from the preparation. Tokens are encoded in a compact binary form,
+//! which is in turn encoded in base45 to fit the alphanumeric mode of a QR code.
+//!
+//! The status is proven as of the block of the token, so a mail item rejected, expired
+//! or cancelled later is detected only by tokens issued afterwards.
+
+use exonum::{
+    blockchain::{self, Block, Blockchain, Transaction},
+    crypto::{CryptoHash, Hash, PublicKey, HASH_SIZE}, helpers::{Height, ValidatorId},
+    messages::{Message, MessageBuffer, Precommit, RawMessage}, storage::{MapProof, Snapshot},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{self, Value};
+
+use std::collections::HashSet;
+
+use schema::{CurrencySchema, MAIL_ACCEPTED, MAIL_PENDING};
+use transactions::WalletTransactions;
+use POST_SERVICE_ID;
+
+/// Version of the binary token format.
+pub const TOKEN_VERSION: u8 = 2;
+
+/// Index of the mail statuses table in the service state hash.
+const MAIL_STATUSES_TABLE: usize = 15;
+
+/// Alphabet of the base45 encoding, see RFC 9285.
+const BASE45_ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Error of decoding or verifying a token.
+#[derive(Debug, Clone, Copy, PartialEq, Fail)]
+pub enum TokenError {
+    /// Token is not valid base45.
+    #[fail(display = "Invalid base45 encoding")]
+    InvalidEncoding,
+
+    /// Token is truncated or contains malformed data.
+    #[fail(display = "Malformed token")]
+    Malformed,
+
+    /// Token uses an unknown format version.
+    #[fail(display = "Unsupported token version")]
+    UnsupportedVersion,
+
+    /// A precommit is not signed by a known validator or is for another block.
+    #[fail(display = "Invalid precommit")]
+    InvalidPrecommit,
+
+    /// Block is not signed by more than two thirds of the validators.
+    #[fail(display = "Not enough precommits")]
+    NotEnoughPrecommits,
+
+    /// Token doesn't hold a mail preparation of the service signed by its sender.
+    #[fail(display = "Invalid mail preparation")]
+    InvalidPreparation,
+
+    /// Proofs don't match the state hash of the block.
+    #[fail(display = "Invalid status proof")]
+    InvalidProof,
+
+    /// Mail item has no status, so its preparation has failed or is not committed.
+    #[fail(display = "Mail item is not prepared")]
+    MailItemNotPrepared,
+
+    /// Mail item is rejected, expired or cancelled.
+    #[fail(display = "Mail item is void")]
+    MailItemVoid,
+}
+
+/// Mail preparation with the proof of its status in the blockchain.
+#[derive(Debug)]
+pub struct PostageToken {
+    /// Hash of the mail preparation transaction.
+    pub mail_item: Hash,
+    /// Signed mail preparation transaction.
+    pub preparation: RawMessage,
+    /// Latest block at the issue of the token.
+    pub block: Block,
+    /// Precommits of the block.
+    pub precommits: Vec<Precommit>,
+    /// Proof of the mail statuses table in the state hash of the block.
+    pub to_table: MapProof<Hash, Hash>,
+    /// Proof of the mail item status in the mail statuses table.
+    pub status_proof: MapProof<Hash, u64>,
+}
+
+impl PostageToken {
+    /// Builds the token for the committed mail item transaction at the latest block,
+    /// or returns `None` if the transaction is not committed.
+    pub fn new<T: AsRef<dyn Snapshot> + Copy>(snapshot: T, mail_item: &Hash) -> Option<Self> {
+        let general_schema = blockchain::Schema::new(snapshot);
+        let preparation = general_schema.transactions().get(mail_item)?;
+        let max_height = general_schema.block_hashes_by_height().len() - 1;
+        let block_proof = general_schema.block_and_precommits(Height(max_height))?;
+        let to_table =
+            general_schema.get_proof_to_service_table(POST_SERVICE_ID, MAIL_STATUSES_TABLE);
+        let status_proof = CurrencySchema::new(snapshot).mail_statuses().get_proof(*mail_item);
+        Some(PostageToken {
+            mail_item: *mail_item,
+            preparation,
+            block: block_proof.block,
+            precommits: block_proof.precommits,
+            to_table,
+            status_proof,
+        })
+    }
+
+    /// Verifies the token against the consensus keys of the validators.
+    ///
+    /// Returns the status of the mail item, which is either `MAIL_PENDING`
+    /// or `MAIL_ACCEPTED`.
+    pub fn verify(&self, validators: &[PublicKey]) -> Result<u64, TokenError> {
+        let block_hash = self.block.hash();
+        let mut signed = HashSet::new();
+        for precommit in &self.precommits {
+            let key = validators
+                .get(precommit.validator().0 as usize)
+                .ok_or(TokenError::InvalidPrecommit)?;
+            if *precommit.block_hash() != block_hash
+                || precommit.height() != self.block.height()
+                || !precommit.verify_signature(key)
+            {
+                return Err(TokenError::InvalidPrecommit);
+            }
+            signed.insert(precommit.validator());
+        }
+        if signed.len() * 3 <= validators.len() * 2 {
+            return Err(TokenError::NotEnoughPrecommits);
+        }
+
+        let (mail_item, signed) = parse_preparation(&self.preparation)?;
+        if !signed || mail_item != self.mail_item {
+            return Err(TokenError::InvalidPreparation);
+        }
+
+        let to_table = self.to_table.check().map_err(|_| TokenError::InvalidProof)?;
+        if to_table.merkle_root() != *self.block.state_hash() {
+            return Err(TokenError::InvalidProof);
+        }
+        let table_key = Blockchain::service_table_unique_key(POST_SERVICE_ID, MAIL_STATUSES_TABLE);
+        let table_hash = to_table
+            .entries()
+            .into_iter()
+            .find(|&(key, _)| *key == table_key)
+            .map(|(_, hash)| *hash)
+            .ok_or(TokenError::InvalidProof)?;
+        let statuses = self.status_proof.check().map_err(|_| TokenError::InvalidProof)?;
+        if statuses.merkle_root() != table_hash {
+            return Err(TokenError::InvalidProof);
+        }
+        let status = statuses
+            .entries()
+            .into_iter()
+            .find(|&(key, _)| *key == self.mail_item)
+            .map(|(_, status)| *status)
+            .ok_or(TokenError::MailItemNotPrepared)?;
+        match status {
+            MAIL_PENDING | MAIL_ACCEPTED => Ok(status),
+            _ => Err(TokenError::MailItemVoid),
+        }
+    }
+
+    /// Encodes the token in the compact binary form.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![TOKEN_VERSION];
+        write_chunk(&mut out, raw_bytes(&self.preparation));
+        write_uint(&mut out, u64::from(self.block.proposer_id().0), 2);
+        write_uint(&mut out, self.block.height().0, 8);
+        write_uint(&mut out, u64::from(self.block.tx_count()), 4);
+        out.extend_from_slice(self.block.prev_hash().as_ref());
+        out.extend_from_slice(self.block.tx_hash().as_ref());
+        out.extend_from_slice(self.block.state_hash().as_ref());
+        out.push(self.precommits.len() as u8);
+        for precommit in &self.precommits {
+            write_chunk(&mut out, raw_bytes(precommit.raw()));
+        }
+        write_map_proof(&mut out, &self.to_table);
+        write_map_proof(&mut out, &self.status_proof);
+        out
+    }
+
+    /// Decodes the token from the compact binary form.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TokenError> {
+        let mut reader = Reader { bytes };
+        if reader.byte()? != TOKEN_VERSION {
+            return Err(TokenError::UnsupportedVersion);
+        }
+        let preparation = RawMessage::new(MessageBuffer::from_vec(reader.chunk()?.to_vec()));
+        let (mail_item, _) = parse_preparation(&preparation)?;
+        let proposer_id = ValidatorId(reader.uint(2)? as u16);
+        let height = Height(reader.uint(8)?);
+        let tx_count = reader.uint(4)? as u32;
+        let (prev_hash, tx_hash, state_hash) = (reader.hash()?, reader.hash()?, reader.hash()?);
+        let block = Block::new(proposer_id, height, tx_count, &prev_hash, &tx_hash, &state_hash);
+        let mut precommits = Vec::new();
+        for _ in 0..reader.byte()? {
+            let raw = RawMessage::new(MessageBuffer::from_vec(reader.chunk()?.to_vec()));
+            precommits.push(Precommit::from_raw(raw).map_err(|_| TokenError::Malformed)?);
+        }
+        let to_table = read_map_proof(&mut reader)?;
+        let status_proof = read_map_proof(&mut reader)?;
+        if !reader.bytes.is_empty() {
+            return Err(TokenError::Malformed);
+        }
+        Ok(PostageToken {
+            mail_item,
+            preparation,
+            block,
+            precommits,
+            to_table,
+            status_proof,
+        })
+    }
+
+    /// Encodes the token in base45 for the alphanumeric mode of a QR code.
+    pub fn to_base45(&self) -> String {
+        base45_encode(&self.to_bytes())
+    }
+
+    /// Decodes the token from base45.
+    pub fn from_base45(s: &str) -> Result<Self, TokenError> {
+        Self::from_bytes(&base45_decode(s)?)
+    }
+}
+
+/// Parses the mail preparation of the service and returns its hash and whether
+/// its signature is valid.
+fn parse_preparation(raw: &RawMessage) -> Result<(Hash, bool), TokenError> {
+    if raw.service_id() != POST_SERVICE_ID {
+        return Err(TokenError::InvalidPreparation);
+    }
+    match WalletTransactions::tx_from_raw(raw.clone()) {
+        Ok(WalletTransactions::MailPreparation(tx)) => Ok((tx.hash(), tx.verify())),
+        Ok(WalletTransactions::AssetMailPreparation(tx)) => Ok((tx.hash(), tx.verify())),
+        Ok(WalletTransactions::StampMailPreparation(tx)) => Ok((tx.hash(), tx.verify())),
+        _ => Err(TokenError::InvalidPreparation),
+    }
+}
+
+/// Returns the bytes of the raw message.
+fn raw_bytes(raw: &RawMessage) -> &[u8] {
+    let buffer: &MessageBuffer = raw;
+    buffer.as_ref()
+}
+
+/// Writes the lowest `len` bytes of the integer in big-endian order.
+fn write_uint(out: &mut Vec<u8>, value: u64, len: usize) {
+    for i in (0..len).rev() {
+        out.push((value >> (8 * i)) as u8);
+    }
+}
+
+/// Writes a chunk of bytes prefixed with its length.
+fn write_chunk(out: &mut Vec<u8>, chunk: &[u8]) {
+    write_uint(out, chunk.len() as u64, 2);
+    out.extend_from_slice(chunk);
+}
+
+/// Map proof in its serialized form, see `MapProof`.
+#[derive(Serialize, Deserialize)]
+struct MapProofNodes {
+    entries: Value,
+    proof: Vec<MapProofNode>,
+}
+
+/// Hash of a subtree of the proof with the bit path to its root.
+#[derive(Serialize, Deserialize)]
+struct MapProofNode {
+    path: String,
+    hash: Hash,
+}
+
+/// Writes the proved entries of the map proof as JSON and the proof nodes as paths
+/// packed into bits followed by hashes.
+fn write_map_proof<K: Serialize, V: Serialize>(out: &mut Vec<u8>, proof: &MapProof<K, V>) {
+    let proof = serde_json::to_value(proof)
+        .and_then(serde_json::from_value::<MapProofNodes>)
+        .expect("Map proofs are serializable");
+    write_chunk(out, proof.entries.to_string().as_bytes());
+    out.push(proof.proof.len() as u8);
+    for node in &proof.proof {
+        write_uint(out, node.path.len() as u64, 2);
+        let mut bits = vec![0; (node.path.len() + 7) / 8];
+        for (i, bit) in node.path.bytes().enumerate() {
+            if bit == b'1' {
+                bits[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        out.extend_from_slice(&bits);
+        out.extend_from_slice(node.hash.as_ref());
+    }
+}
+
+fn read_map_proof<K, V>(reader: &mut Reader) -> Result<MapProof<K, V>, TokenError>
+where
+    MapProof<K, V>: DeserializeOwned,
+{
+    let entries = serde_json::from_slice(reader.chunk()?).map_err(|_| TokenError::Malformed)?;
+    let mut proof = Vec::new();
+    for _ in 0..reader.byte()? {
+        let len = reader.uint(2)? as usize;
+        if len > HASH_SIZE * 8 {
+            return Err(TokenError::Malformed);
+        }
+        let bits = reader.take((len + 7) / 8)?;
+        let path = (0..len)
+            .map(|i| if bits[i / 8] & (0x80 >> (i % 8)) != 0 { '1' } else { '0' })
+            .collect();
+        proof.push(MapProofNode {
+            path,
+            hash: reader.hash()?,
+        });
+    }
+    serde_json::to_value(MapProofNodes { entries, proof })
+        .and_then(serde_json::from_value)
+        .map_err(|_| TokenError::Malformed)
+}
+
+/// Reads the binary token.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], TokenError> {
+        if self.bytes.len() < len {
+            return Err(TokenError::Malformed);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8, TokenError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn hash(&mut self) -> Result<Hash, TokenError> {
+        Hash::from_slice(self.take(HASH_SIZE)?).ok_or(TokenError::Malformed)
+    }
+
+    fn uint(&mut self, len: usize) -> Result<u64, TokenError> {
+        let bytes = self.take(len)?;
+        Ok(bytes.iter().fold(0, |value, &byte| (value << 8) | u64::from(byte)))
+    }
+
+    fn chunk(&mut self) -> Result<&'a [u8], TokenError> {
+        let len = self.uint(2)? as usize;
+        self.take(len)
+    }
+}
+
+/// Encodes bytes in base45 as defined by RFC 9285.
+pub fn base45_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() / 2 * 3 + 2);
+    for pair in bytes.chunks(2) {
+        let (mut n, digits) = if pair.len() == 2 {
+            (usize::from(pair[0]) * 256 + usize::from(pair[1]), 3)
+        } else {
+            (usize::from(pair[0]), 2)
+        };
+        for _ in 0..digits {
+            out.push(char::from(BASE45_ALPHABET[n % 45]));
+            n /= 45;
+        }
+    }
+    out
+}
+
+/// Decodes base45 as defined by RFC 9285.
+pub fn base45_decode(s: &str) -> Result<Vec<u8>, TokenError> {
+    let digits = s
+        .bytes()
+        .map(|c| {
+            BASE45_ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .ok_or(TokenError::InvalidEncoding)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut out = Vec::with_capacity(digits.len() / 3 * 2 + 1);
+    for group in digits.chunks(3) {
+        let n = group.iter().rev().fold(0, |n, &digit| n * 45 + digit);
+        match group.len() {
+            3 if n <= 0xffff => {
+                out.push((n >> 8) as u8);
+                out.push(n as u8);
+            }
+            2 if n <= 0xff => out.push(n as u8),
+            _ => return Err(TokenError::InvalidEncoding),
+        }
+    }
+    Ok(out)
+}
@@ -21,17 +21,29 @@ extern crate serde;
 use serde::{Deserialize, Serialize, Deserializer, Serializer};
 
 
-use exonum::blockchain::{ExecutionError, ExecutionResult, Transaction};
-use exonum::crypto::{CryptoHash, PublicKey, Hash};
-use exonum::messages::Message;
-use exonum::storage::Fork;
+use exonum::blockchain::{self, ExecutionError, ExecutionResult, Transaction};
+use exonum::crypto::{self, CryptoHash, PublicKey, Hash, Signature};
+use exonum::messages::{Message, ServiceMessage};
+use exonum::storage::{Fork, Snapshot};
 use exonum_time::schema::TimeSchema;
+use chrono::{DateTime, TimeZone, Utc};
 
-use POST_SERVICE_ID;
-use schema::{CurrencySchema, TimestampEntry};
+use {MAX_INDEX_BATCH, MAX_MINT_BATCH, POST_SERVICE_ID, PREPARATION_TTL};
+use amount::Amount;
+use config::{ServiceConfig, TimePolicy};
+use events::PostEvent;
+use schema::{
+    day_bucket, normalize_name, stamp_id, Asset, AssetPreparation, CurrencySchema, Invoice,
+    Limits, NameRecord, PendingTransfer, Redemption, Stamp, StandingOrder, StandingOrderPayment,
+    TimestampEntry, Voucher, MAIL_ACCEPTED, MAIL_CANCELLED, MAIL_EXPIRED, MAIL_PENDING,
+    MAIL_REJECTED, MAX_WALLET_NAMES,
+};
+use wallet::Wallet;
 
 /// Error codes emitted by wallet transactions during execution.
-#[derive(Debug, Fail)]
+///
+/// Codes are stable: new errors get new codes and existing codes are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Fail)]
 #[repr(u8)]
 pub enum Error {
     /// Wallet already exists.
@@ -48,24 +60,367 @@ pub enum Error {
 
     /// Receiver doesn't exist.
     ///
-    /// Can be emitted by `Transfer` or `Issue`.
+    /// Can be emitted by `Transfer`, `Issue` or `ExecuteScheduled`.
     #[fail(display = "Receiver doesn't exist")]
     ReceiverNotFound = 2,
 
     /// Insufficient currency amount.
     ///
-    /// Can be emitted by `Transfer`.
+    /// Can be emitted by any transaction decreasing a balance.
     #[fail(display = "Insufficient currency amount")]
     InsufficientCurrencyAmount = 3,
 
+    /// Cancellation period of the transaction is over.
+    ///
+    /// Can be emitted by `Cancellation`.
     #[fail(display = "Time is up")]
     Timeisup = 4,
 
+    /// Signer is not an inspector.
+    ///
+    /// Can be emitted by `MailAcceptance` or `Cancellation`.
     #[fail(display = "Pubkey doesn`t belong to inspector")]
     NotInspector = 5,
 
+    /// Signer is not an issuer.
+    ///
+    /// Can be emitted by `Issue`.
     #[fail(display = "Pubkey doesn`t belong to issuer")]
     NotIssuer = 6,
+
+    /// Receiver key is forwarded to the sender's own wallet.
+    ///
+    /// Can be emitted by `Transfer`.
+    #[fail(display = "Receiver is the sender")]
+    ReceiverIsSender = 7,
+
+    /// Wallet is suspended by a compliance officer.
+    ///
+    /// Can be emitted by `Transfer`, `MailPreparation`, or by `SuspendWallet`,
+    /// `UnsuspendWallet`, `SetWalletLimits` and `SetRoleLimits` signed by a suspended
+    /// compliance officer.
+    #[fail(display = "Wallet is suspended")]
+    WalletSuspended = 8,
+
+    /// Signer is not a compliance officer.
+    ///
+    /// Can be emitted by `SuspendWallet`, `UnsuspendWallet`, `SetWalletLimits` or
+    /// `SetRoleLimits`.
+    #[fail(display = "Pubkey doesn`t belong to compliance officer")]
+    NotComplianceOfficer = 9,
+
+    /// Wallet doesn't exist.
+    ///
+    /// Can be emitted by `SuspendWallet`, `UnsuspendWallet` or `AssignRole`.
+    #[fail(display = "Wallet doesn't exist")]
+    WalletNotFound = 10,
+
+    /// Wallet has frozen funds of mail items not yet accepted or rejected.
+    ///
+    /// Can be emitted by `CloseWallet`, `MailPreparation` or `AssetMailPreparation`.
+    #[fail(display = "Wallet has open mail items")]
+    OpenMailItems = 11,
+
+    /// Amount exceeds the maximal single transfer of the wallet.
+    ///
+    /// Can be emitted by `Transfer` or `CloseWallet`.
+    #[fail(display = "Transfer limit exceeded")]
+    TransferLimitExceeded = 12,
+
+    /// Amount exceeds the daily outbound volume of the wallet.
+    ///
+    /// Can be emitted by `Transfer` or `CloseWallet`.
+    #[fail(display = "Daily outbound limit exceeded")]
+    DailyOutboundLimitExceeded = 13,
+
+    /// Amount exceeds the daily issue volume of the issuer.
+    ///
+    /// Can be emitted by `Issue`.
+    #[fail(display = "Daily issue limit exceeded")]
+    DailyIssueLimitExceeded = 14,
+
+    /// Scheduled transfer doesn't exist or is already executed.
+    ///
+    /// Can be emitted by `ExecuteScheduled`.
+    #[fail(display = "Scheduled transfer doesn't exist")]
+    ScheduledTransferNotFound = 15,
+
+    /// Execution time of the scheduled transfer or standing order payment has not come yet.
+    ///
+    /// Can be emitted by `ExecuteScheduled` or `ExecuteStandingOrder`.
+    #[fail(display = "Scheduled time is not reached")]
+    ScheduledTimeNotReached = 16,
+
+    /// Standing order doesn't exist or is already finished.
+    ///
+    /// Can be emitted by `CancelStandingOrder` or `ExecuteStandingOrder`.
+    #[fail(display = "Standing order doesn't exist")]
+    StandingOrderNotFound = 17,
+
+    /// Signer is not the payer of the standing order.
+    ///
+    /// Can be emitted by `CancelStandingOrder`.
+    #[fail(display = "Pubkey doesn`t belong to standing order payer")]
+    NotStandingOrderPayer = 18,
+
+    /// Invoice doesn't exist.
+    ///
+    /// Can be emitted by `PayInvoice`.
+    #[fail(display = "Invoice doesn't exist")]
+    InvoiceNotFound = 19,
+
+    /// Invoice is expired.
+    ///
+    /// Can be emitted by `PayInvoice`.
+    #[fail(display = "Invoice is expired")]
+    InvoiceExpired = 20,
+
+    /// Invoice is already paid.
+    ///
+    /// Can be emitted by `PayInvoice`.
+    #[fail(display = "Invoice is already paid")]
+    InvoiceAlreadyPaid = 21,
+
+    /// Signer is not the payer of the invoice.
+    ///
+    /// Can be emitted by `PayInvoice`.
+    #[fail(display = "Pubkey doesn`t belong to invoice payer")]
+    NotInvoicePayer = 22,
+
+    /// Name is already registered.
+    ///
+    /// Can be emitted by `RegisterName`.
+    #[fail(display = "Name is already taken")]
+    NameAlreadyTaken = 23,
+
+    /// Name is not registered to the signer.
+    ///
+    /// Can be emitted by `ReleaseName`.
+    #[fail(display = "Name doesn`t belong to the wallet")]
+    NameNotOwned = 24,
+
+    /// Signer is not a validator.
+    ///
+    /// Can be emitted by `ExpirePreparation`, `AssignRole`, `ExecuteScheduled`,
+    /// `ExecuteStandingOrder` or `IndexTimestamps`.
+    #[fail(display = "Pubkey doesn`t belong to validator")]
+    NotValidator = 25,
+
+    /// Wallet has no mail preparation waiting for acceptance.
+    ///
+    /// Can be emitted by `ExpirePreparation`.
+    #[fail(display = "Preparation doesn't exist")]
+    PreparationNotFound = 26,
+
+    /// Mail preparation is not old enough to be expired.
+    ///
+    /// Can be emitted by `ExpirePreparation`.
+    #[fail(display = "Preparation is not expired")]
+    PreparationNotExpired = 27,
+
+    /// Time oracle has not reported the time yet and the `TimePolicy` is `Reject`.
+    ///
+    /// Can be emitted by any transaction depending on the time.
+    #[fail(display = "Time is not available")]
+    TimeNotAvailable = 28,
+
+    /// Transaction doesn't exist or has no timestamp.
+    ///
+    /// Can be emitted by `Cancellation`.
+    #[fail(display = "Transaction doesn't exist")]
+    TransactionNotFound = 29,
+
+    /// Transaction cannot be cancelled.
+    ///
+    /// Can be emitted by `Cancellation`.
+    #[fail(display = "Transaction type is not supported")]
+    UnknownTransactionType = 30,
+
+    /// Balance of the receiver would overflow.
+    ///
+    /// Can be emitted by any transaction increasing a balance.
+    #[fail(display = "Balance overflow")]
+    BalanceOverflow = 31,
+
+    /// Asset is not registered.
+    ///
+    /// Can be emitted by `IssueAsset`, `TransferAsset` or `AssetMailPreparation`.
+    #[fail(display = "Asset doesn't exist")]
+    AssetNotFound = 32,
+
+    /// Signer is not the issuer of the asset.
+    ///
+    /// Can be emitted by `IssueAsset`.
+    #[fail(display = "Pubkey doesn`t belong to asset issuer")]
+    NotAssetIssuer = 33,
+
+    /// Vouchers can only be spent on mail.
+    ///
+    /// Can be emitted by `TransferAsset`.
+    #[fail(display = "Voucher is not transferable")]
+    VoucherNotTransferable = 34,
+
+    /// Voucher is void after its expiry time.
+    ///
+    /// Can be emitted by `IssueAsset` or `AssetMailPreparation`.
+    #[fail(display = "Voucher is expired")]
+    VoucherExpired = 35,
+
+    /// Stamp with the same serial number already exists in the series.
+    ///
+    /// Can be emitted by `MintStamps`.
+    #[fail(display = "Stamp already exists")]
+    StampAlreadyExists = 36,
+
+    /// Stamp doesn't exist.
+    ///
+    /// Can be emitted by `TransferStamp` or `StampMailPreparation`.
+    #[fail(display = "Stamp doesn't exist")]
+    StampNotFound = 37,
+
+    /// Signer doesn't own the stamp.
+    ///
+    /// Can be emitted by `TransferStamp` or `StampMailPreparation`.
+    #[fail(display = "Pubkey doesn`t belong to stamp owner")]
+    NotStampOwner = 38,
+
+    /// Stamp is already used for a mail item.
+    ///
+    /// Can be emitted by `TransferStamp` or `StampMailPreparation`.
+    #[fail(display = "Stamp is already used")]
+    StampAlreadyUsed = 39,
+
+    /// Signer is not a sorting machine.
+    ///
+    /// Can be emitted by `RedeemStamp`.
+    #[fail(display = "Pubkey doesn`t belong to sorting machine")]
+    NotSortingMachine = 40,
+
+    /// Stamp is not used for a mail item accepted by an inspector.
+    ///
+    /// Can be emitted by `RedeemStamp`.
+    #[fail(display = "Mail item is not accepted")]
+    MailItemNotAccepted = 41,
+
+    /// Stamp is already redeemed.
+    ///
+    /// Can be emitted by `RedeemStamp`.
+    #[fail(display = "Stamp is already redeemed")]
+    StampAlreadyRedeemed = 42,
+
+    /// Time is in the past.
+    ///
+    /// Can be emitted by `CreateStandingOrder` and `CreateVoucher`.
+    #[fail(display = "Time is in the past")]
+    TimeInPast = 43,
+
+    /// Wallet has too many names.
+    ///
+    /// Can be emitted by `RegisterName`.
+    #[fail(display = "Wallet has too many names")]
+    TooManyNames = 44,
+
+    /// Acceptance of a mail item paid with assets or stamps can't be cancelled.
+    ///
+    /// Can be emitted by `Cancellation`.
+    #[fail(display = "Acceptance can't be cancelled")]
+    AcceptanceNotCancellable = 45,
+
+    /// Transaction is already cancelled.
+    ///
+    /// Can be emitted by `Cancellation`.
+    #[fail(display = "Transaction is already cancelled")]
+    TransactionAlreadyCancelled = 46,
+
+    /// Mail item is already accepted, rejected, expired or cancelled.
+    ///
+    /// Can be emitted by `Cancellation`.
+    #[fail(display = "Mail item is not pending")]
+    MailItemNotPending = 47,
+
+    /// Name is empty, mixes scripts or contains disallowed characters.
+    ///
+    /// Can be emitted by `RegisterName` and `ReleaseName`.
+    #[fail(display = "Invalid name")]
+    InvalidName = 48,
+}
+
+/// Entry of the error code catalogue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorDescription {
+    /// Code returned in the transaction status.
+    pub code: u8,
+    /// Name of the error.
+    pub name: String,
+    /// Description returned in the transaction status.
+    pub description: String,
+}
+
+impl Error {
+    /// All errors in the order of their codes.
+    pub const ALL: &'static [Error] = &[
+        Error::WalletAlreadyExists,
+        Error::SenderNotFound,
+        Error::ReceiverNotFound,
+        Error::InsufficientCurrencyAmount,
+        Error::Timeisup,
+        Error::NotInspector,
+        Error::NotIssuer,
+        Error::ReceiverIsSender,
+        Error::WalletSuspended,
+        Error::NotComplianceOfficer,
+        Error::WalletNotFound,
+        Error::OpenMailItems,
+        Error::TransferLimitExceeded,
+        Error::DailyOutboundLimitExceeded,
+        Error::DailyIssueLimitExceeded,
+        Error::ScheduledTransferNotFound,
+        Error::ScheduledTimeNotReached,
+        Error::StandingOrderNotFound,
+        Error::NotStandingOrderPayer,
+        Error::InvoiceNotFound,
+        Error::InvoiceExpired,
+        Error::InvoiceAlreadyPaid,
+        Error::NotInvoicePayer,
+        Error::NameAlreadyTaken,
+        Error::NameNotOwned,
+        Error::NotValidator,
+        Error::PreparationNotFound,
+        Error::PreparationNotExpired,
+        Error::TimeNotAvailable,
+        Error::TransactionNotFound,
+        Error::UnknownTransactionType,
+        Error::BalanceOverflow,
+        Error::AssetNotFound,
+        Error::NotAssetIssuer,
+        Error::VoucherNotTransferable,
+        Error::VoucherExpired,
+        Error::StampAlreadyExists,
+        Error::StampNotFound,
+        Error::NotStampOwner,
+        Error::StampAlreadyUsed,
+        Error::NotSortingMachine,
+        Error::MailItemNotAccepted,
+        Error::StampAlreadyRedeemed,
+        Error::TimeInPast,
+        Error::TooManyNames,
+        Error::AcceptanceNotCancellable,
+        Error::TransactionAlreadyCancelled,
+        Error::MailItemNotPending,
+        Error::InvalidName,
+    ];
+
+    /// Returns the catalogue of all error codes.
+    pub fn catalogue() -> Vec<ErrorDescription> {
+        Error::ALL
+            .iter()
+            .map(|error| ErrorDescription {
+                code: *error as u8,
+                name: format!("{:?}", error),
+                description: error.to_string(),
+            })
+            .collect()
+    }
 }
 
 impl From<Error> for ExecutionError {
@@ -83,7 +438,7 @@ transactions! {
         struct Transfer {
             from:    &PublicKey,
             to:      &PublicKey,
-            amount:  u64,
+            amount:  Amount,
             seed:    u64,
         }
 
@@ -91,11 +446,12 @@ transactions! {
         struct Issue {
             pub_key:  &PublicKey,
             issuer_key: &PublicKey,
-            amount:  u64,
+            amount:  Amount,
             seed:    u64,
         }
 
-        /// Create wallet with the given `name`. 1 - inspector, 0 - user, 2 - issuer
+        /// Create wallet with the given `name`.
+        /// 1 - inspector, 0 - user, 2 - issuer. Other roles are assigned by `AssignRole`.
         struct CreateWallet {
             pub_key: &PublicKey,
             name:    &str,
@@ -106,7 +462,7 @@ transactions! {
         struct MailPreparation {
             meta: &str,
             pub_key: &PublicKey,
-            amount: u64,
+            amount: Amount,
             seed: u64,
         }
 
@@ -114,7 +470,7 @@ transactions! {
         struct MailAcceptance {
             pub_key: &PublicKey,
             sender_key: &PublicKey,
-            amount: u64,
+            amount: Amount,
             accept:  bool,
             seed: u64,
         }
@@ -125,6 +481,351 @@ transactions! {
             sender: &PublicKey,
             tx_hash: &Hash,
         }
+
+        /// Move the wallet to `new_pub_key`. `new_key_signature` is a signature
+        /// of `rotation_payload` made with the new secret key.
+        struct RotateKey {
+            pub_key:           &PublicKey,
+            new_pub_key:       &PublicKey,
+            new_key_signature: &Signature,
+            seed:              u64,
+        }
+
+        /// Suspend the `wallet`. Signed by a compliance officer.
+        struct SuspendWallet {
+            pub_key: &PublicKey,
+            wallet:  &PublicKey,
+            seed:    u64,
+        }
+
+        /// Lift the suspension of the `wallet`. Signed by a compliance officer.
+        struct UnsuspendWallet {
+            pub_key: &PublicKey,
+            wallet:  &PublicKey,
+            seed:    u64,
+        }
+
+        /// Close the wallet and transfer its remaining balance to the `beneficiary`.
+        /// Pending scheduled transfers of the wallet are cancelled and its names are released.
+        /// Balances in registered assets are moved as well, except vouchers, which become void.
+        struct CloseWallet {
+            pub_key:     &PublicKey,
+            beneficiary: &PublicKey,
+            seed:        u64,
+        }
+
+        /// Set spending limits of the `wallet`. Signed by a compliance officer.
+        /// Zero means no limit.
+        struct SetWalletLimits {
+            pub_key:        &PublicKey,
+            wallet:         &PublicKey,
+            max_transfer:   u64,
+            daily_outbound: u64,
+            daily_issue:    u64,
+            seed:           u64,
+        }
+
+        /// Set spending limits of wallets with the given `role`, see `CreateWallet`.
+        /// Signed by a compliance officer. Zero means no limit.
+        struct SetRoleLimits {
+            pub_key:        &PublicKey,
+            role:           u64,
+            max_transfer:   u64,
+            daily_outbound: u64,
+            daily_issue:    u64,
+            seed:           u64,
+        }
+
+        /// Reserve `amount` of the currency and transfer it to `to` at or after `execute_at`.
+        struct ScheduledTransfer {
+            from:       &PublicKey,
+            to:         &PublicKey,
+            amount:     Amount,
+            execute_at: DateTime<Utc>,
+            seed:       u64,
+        }
+
+        /// Execute the scheduled transfer created by the transaction with `tx_hash`.
+        /// Signed by a validator service key once the time of the transfer has come.
+        struct ExecuteScheduled {
+            pub_key: &PublicKey,
+            tx_hash: &Hash,
+            seed:    u64,
+        }
+
+        /// Pay `amount` from `payer` to `payee` every `period` seconds starting at `start`
+        /// and until `end`.
+        struct CreateStandingOrder {
+            payer:  &PublicKey,
+            payee:  &PublicKey,
+            amount: Amount,
+            period: u64,
+            start:  DateTime<Utc>,
+            end:    DateTime<Utc>,
+            seed:   u64,
+        }
+
+        /// Cancel the standing `order`. Signed by the payer.
+        struct CancelStandingOrder {
+            pub_key: &PublicKey,
+            order:   &Hash,
+            seed:    u64,
+        }
+
+        /// Make the due payment of the standing `order`.
+        /// Signed by a validator service key at period boundaries.
+        struct ExecuteStandingOrder {
+            pub_key: &PublicKey,
+            order:   &Hash,
+            seed:    u64,
+        }
+
+        /// Request `amount` from `payer`. Signed by the payee.
+        struct CreateInvoice {
+            payee:      &PublicKey,
+            payer:      &PublicKey,
+            amount:     Amount,
+            reference:  &str,
+            expires_at: DateTime<Utc>,
+            seed:       u64,
+        }
+
+        /// Pay the `invoice`. Signed by the payer.
+        struct PayInvoice {
+            payer:   &PublicKey,
+            invoice: &Hash,
+            seed:    u64,
+        }
+
+        /// Transfer `amount` of the currency from one wallet to another with a `reference`
+        /// for reconciliation in external accounting.
+        struct TransferWithReference {
+            from:      &PublicKey,
+            to:        &PublicKey,
+            amount:    Amount,
+            seed:      u64,
+            reference: &str,
+        }
+
+        /// Register `name` in the wallet name directory. A wallet may hold up to
+        /// `MAX_WALLET_NAMES` names.
+        struct RegisterName {
+            pub_key: &PublicKey,
+            name:    &str,
+            seed:    u64,
+        }
+
+        /// Release `name` registered by the wallet.
+        struct ReleaseName {
+            pub_key: &PublicKey,
+            name:    &str,
+            seed:    u64,
+        }
+
+        /// Return frozen funds of a stale mail preparation of the `wallet`.
+        /// Signed by a validator service key.
+        struct ExpirePreparation {
+            pub_key: &PublicKey,
+            wallet:  &PublicKey,
+            seed:    u64,
+        }
+
+        /// Register an asset with the given `name`. Signed by an issuer, who becomes
+        /// the only issuer of the asset. The asset is identified by the transaction hash.
+        struct CreateAsset {
+            issuer: &PublicKey,
+            name:   &str,
+            seed:   u64,
+        }
+
+        /// Issue `amount` of the `asset` to the wallet. The zero `asset` stands for
+        /// postage units and behaves like `Issue`. Issuer limits apply to postage only.
+        struct IssueAsset {
+            pub_key:    &PublicKey,
+            issuer_key: &PublicKey,
+            asset:      &Hash,
+            amount:     Amount,
+            seed:       u64,
+        }
+
+        /// Transfer `amount` of the `asset` from one wallet to another. The zero `asset`
+        /// stands for postage units and behaves like `Transfer`. Spending limits apply
+        /// to postage only.
+        struct TransferAsset {
+            from:   &PublicKey,
+            to:     &PublicKey,
+            asset:  &Hash,
+            amount: Amount,
+            seed:   u64,
+        }
+
+        /// Prepare `amount` of the `asset` for stamping. The zero `asset` stands for
+        /// postage units and behaves like `MailPreparation`.
+        struct AssetMailPreparation {
+            meta:    &str,
+            pub_key: &PublicKey,
+            asset:   &Hash,
+            amount:  Amount,
+            seed:    u64,
+        }
+
+        /// Register a voucher asset with the given `name` that can only be spent on mail
+        /// and becomes void after `expires_at`. Signed by an issuer, see `CreateAsset`.
+        struct CreateVoucher {
+            issuer:     &PublicKey,
+            name:       &str,
+            expires_at: DateTime<Utc>,
+            seed:       u64,
+        }
+
+        /// Mint `count` stamps of the `series` numbered from `first_number` to the `owner`.
+        /// Signed by an issuer.
+        struct MintStamps {
+            issuer:       &PublicKey,
+            owner:        &PublicKey,
+            series:       &str,
+            first_number: u64,
+            count:        u64,
+            denomination: Amount,
+            artwork:      &Hash,
+            seed:         u64,
+        }
+
+        /// Transfer the unused `stamp` from one wallet to another.
+        struct TransferStamp {
+            from:  &PublicKey,
+            to:    &PublicKey,
+            stamp: &Hash,
+            seed:  u64,
+        }
+
+        /// Prepare a mail item paid with the `stamp`, which is consumed instead of
+        /// the wallet balance.
+        struct StampMailPreparation {
+            meta:    &str,
+            pub_key: &PublicKey,
+            stamp:   &Hash,
+            seed:    u64,
+        }
+
+        /// Mark the `stamp` of an accepted mail item as redeemed. Signed by a sorting
+        /// machine; a stamp can be redeemed only once.
+        struct RedeemStamp {
+            pub_key: &PublicKey,
+            stamp:   &Hash,
+            seed:    u64,
+        }
+
+        /// Assign the `role` to the `wallet`: 3 - compliance officer, 4 - sorting machine.
+        /// Signed by a validator service key.
+        struct AssignRole {
+            pub_key: &PublicKey,
+            wallet:  &PublicKey,
+            role:    u64,
+            seed:    u64,
+        }
+
+        /// Add up to `count` entries timestamped before the time index was introduced
+        /// to the index. Signed by a validator service key.
+        struct IndexTimestamps {
+            pub_key: &PublicKey,
+            count:   u64,
+            seed:    u64,
+        }
+    }
+}
+
+/// Returns the current time reported by the time oracle, or the fallback time
+/// defined by the `TimePolicy` if the oracle has not reported yet.
+fn current_time(fork: &Fork) -> Result<DateTime<Utc>, Error> {
+    if let Some(time) = TimeSchema::new(fork).time().get() {
+        return Ok(time);
+    }
+    match ServiceConfig::actual(fork).time_policy {
+        TimePolicy::Reject => Err(Error::TimeNotAvailable),
+        TimePolicy::BlockHeight => {
+            let height = blockchain::Schema::new(fork).block_hashes_by_height().len();
+            Ok(Utc.timestamp(height as i64, 0))
+        }
+    }
+}
+
+/// Checks whether `pub_key` is the service key of a validator.
+fn is_validator(fork: &Fork, pub_key: &PublicKey) -> bool {
+    blockchain::Schema::new(fork)
+        .actual_configuration()
+        .validator_keys
+        .iter()
+        .any(|keys| keys.service_key == *pub_key)
+}
+
+/// Checks that `sender` may send `amount` on the given `day`: the wallet is not
+/// suspended, has enough funds and stays within its spending limits.
+fn check_outgoing<T>(
+    schema: &CurrencySchema<T>,
+    sender: &Wallet,
+    amount: Amount,
+    day: u64,
+) -> Result<(), Error>
+where
+    T: AsRef<dyn Snapshot>,
+{
+    if sender.suspended() {
+        return Err(Error::WalletSuspended);
+    }
+    if sender.balance() < amount {
+        return Err(Error::InsufficientCurrencyAmount);
+    }
+    if let Some(limits) = schema.limits(sender.pub_key()) {
+        if limits.max_transfer() != 0 && amount.0 > limits.max_transfer() {
+            return Err(Error::TransferLimitExceeded);
+        }
+        let sent = schema.daily_outbound(sender.pub_key()).get(&day).unwrap_or(0);
+        if limits.daily_outbound() != 0 && sent.saturating_add(amount.0) > limits.daily_outbound() {
+            return Err(Error::DailyOutboundLimitExceeded);
+        }
+    }
+    Ok(())
+}
+
+/// Issues `amount` of postage units to `pub_key` on behalf of the `issuer`
+/// and the transaction with `hash`.
+fn issue(
+    fork: &mut Fork,
+    issuer: &PublicKey,
+    pub_key: &PublicKey,
+    amount: Amount,
+    hash: &Hash,
+) -> ExecutionResult {
+    let time = current_time(fork)?;
+    let mut schema = CurrencySchema :: new(fork);
+    if !schema.issuers().contains(issuer) {
+    	Err(Error::NotIssuer)?
+    }
+    if let Some(wallet) = schema.forwarded_wallet(pub_key) {
+        let day = day_bucket(time);
+        if let Some(limits) = schema.limits(issuer) {
+            let issued = schema.daily_issued(issuer).get(&day).unwrap_or(0);
+            if limits.daily_issue() != 0 && issued.saturating_add(amount.0) > limits.daily_issue() {
+                Err(Error::DailyIssueLimitExceeded)?
+            }
+        }
+        let event = PostEvent::Issued {
+            issuer: *issuer,
+            pub_key: *wallet.pub_key(),
+            asset: Hash::zero(),
+            amount,
+        };
+        schema.increase_wallet_balance(wallet, amount, hash, Amount(0))?;
+        schema.add_daily_issued(issuer, day, amount);
+        schema.emit_event(hash, event);
+
+        let entry = TimestampEntry::new(hash, time);
+        schema.add_timestamp(entry);
+
+        Ok(())
+    } else {
+        Err(Error::ReceiverNotFound)?
     }
 }
 
@@ -134,26 +835,45 @@ impl Transaction for Issue {
     }
 
     fn execute(&self, fork: &mut Fork) -> ExecutionResult {
-        let time = TimeSchema::new(&fork)
-            .time()
-            .get();
-        let mut schema = CurrencySchema :: new(fork);
-        let pub_key = self.pub_key();
-        if !schema.issuers().contains(self.issuer_key()) {
-        	Err(Error::NotIssuer)?
-        }
-        if let Some(wallet) = schema.wallet(pub_key) {
-            let amount = self.amount();
-            schema.increase_wallet_balance(wallet, amount, &self.hash(), 0);
+        issue(fork, self.issuer_key(), self.pub_key(), self.amount(), &self.hash())
+    }
+}
 
-            let entry = TimestampEntry::new(&self.hash(), time.unwrap());
-            schema.add_timestamp(entry);
+/// Transfers `amount` from `from` to `to` on behalf of the transaction with `hash`.
+fn transfer(
+    fork: &mut Fork,
+    from: &PublicKey,
+    to: &PublicKey,
+    amount: Amount,
+    hash: &Hash,
+) -> ExecutionResult {
+    let time = current_time(fork)?;
 
-            Ok(())
-        } else {
-            Err(Error::ReceiverNotFound)?
-        }
+    let mut schema = CurrencySchema::new(fork);
+    let freezed_balance = Amount(0);
+
+    let sender = schema.wallet(from).ok_or(Error :: SenderNotFound)?;
+    let receiver = schema.forwarded_wallet(to).ok_or(Error :: ReceiverNotFound)?;
+    if receiver.pub_key() == from {
+        Err(Error::ReceiverIsSender)?;
     }
+    let day = day_bucket(time);
+    check_outgoing(&schema, &sender, amount, day)?;
+
+    let event = PostEvent::Transferred {
+        from: *from,
+        to: *receiver.pub_key(),
+        asset: Hash::zero(),
+        amount,
+    };
+    schema.decrease_wallet_balance(sender, amount, hash, freezed_balance)?;
+    schema.increase_wallet_balance(receiver, amount, hash, freezed_balance)?;
+    schema.add_daily_outbound(from, day, amount);
+    schema.emit_event(hash, event);
+
+    let entry = TimestampEntry::new(hash, time);
+    schema.add_timestamp(entry);
+    Ok(())
 }
 
 impl Transaction for Transfer {
@@ -162,31 +882,7 @@ impl Transaction for Transfer {
     }
 
     fn execute(&self, fork: &mut Fork) -> ExecutionResult {
-        let time = TimeSchema::new(&fork)
-            .time()
-            .get();
-        
-        let mut schema = CurrencySchema::new(fork);
-        let from = self.from();
-        let to = self.to();
-        let hash = self.hash();
-        let amount = self.amount();
-        let freezed_balance = 0;
-
-        let sender = schema.wallet(from).ok_or(Error :: SenderNotFound)?;
-        let receiver = schema.wallet(to).ok_or(Error :: ReceiverNotFound)?;
-
-        if sender.balance() < amount {
-            Err(Error::InsufficientCurrencyAmount)?;
-
-        }
-
-        schema.decrease_wallet_balance(sender, amount, &hash, freezed_balance);
-        schema.increase_wallet_balance(receiver, amount, &hash, freezed_balance);
-        
-        let entry = TimestampEntry::new(&self.hash(), time.unwrap());
-        schema.add_timestamp(entry);
-        Ok(())
+        transfer(fork, self.from(), self.to(), self.amount(), &self.hash())
     }
 }
 
@@ -196,21 +892,33 @@ impl Transaction for CreateWallet {
     }
 
     fn execute(&self, fork: &mut Fork) -> ExecutionResult {
-        let time = TimeSchema::new(&fork)
-            .time()
-            .get();
+        let time = current_time(fork)?;
         let mut schema = CurrencySchema::new(fork);
         let pub_key = self.pub_key();
         let hash = self.hash();
-        if schema.wallet(pub_key).is_none(){
+        if !schema.is_key_used(pub_key) {
             let name = self.name();
-            let freezed_balance = 0;
+            let freezed_balance = Amount(0);
             schema.create_wallet(pub_key, name, &hash, freezed_balance);
 
-            let entry = TimestampEntry::new(&self.hash(), time.unwrap());
+            let entry = TimestampEntry::new(&self.hash(), time);
             schema.add_timestamp(entry);
             schema.add_inspector(pub_key, self.user_type());
             schema.add_issuer(pub_key, self.user_type());
+
+            let event = PostEvent::WalletCreated {
+                pub_key: *pub_key,
+                name: name.to_owned(),
+            };
+            schema.emit_event(&hash, event);
+            let role = schema.wallet_role(pub_key);
+            if role != 0 {
+                let event = PostEvent::RoleAssigned {
+                    pub_key: *pub_key,
+                    role,
+                };
+                schema.emit_event(&hash, event);
+            }
             Ok(())
         } else {
             Err(Error::WalletAlreadyExists)?
@@ -218,27 +926,97 @@ impl Transaction for CreateWallet {
     }    
 }
 
+/// Freezes `amount` of postage units of `pub_key` for a mail item on behalf of
+/// the transaction with `hash`.
+fn prepare_mail(
+    fork: &mut Fork,
+    pub_key: &PublicKey,
+    amount: Amount,
+    hash: &Hash,
+) -> ExecutionResult {
+    let time = current_time(fork)?;
+    let mut schema = CurrencySchema :: new(fork);
+    let sender = schema.wallet(pub_key).ok_or(Error :: SenderNotFound)?;
+    if sender.suspended() {
+        Err(Error::WalletSuspended)?;
+    }
+    if sender.balance() < amount {
+        Err(Error::InsufficientCurrencyAmount)?;
+    }
+    // A wallet has at most one pending preparation, see `AssetMailPreparation`.
+    if sender.freezed_balance() != Amount(0) || schema.pending_preparations().contains(pub_key) {
+        Err(Error::OpenMailItems)?;
+    }
+    schema.decrease_wallet_balance(sender, amount, hash, amount)?;
+    schema.pending_preparations_mut().put(pub_key, *hash);
+    schema.mail_statuses_mut().put(hash, MAIL_PENDING);
+    let event = PostEvent::MailPrepared {
+        pub_key: *pub_key,
+        asset: Hash::zero(),
+        amount,
+    };
+    schema.emit_event(hash, event);
+    let entry = TimestampEntry::new(hash, time);
+    schema.add_timestamp(entry);
+    Ok(())
+}
+
+/// Finishes the pending mail preparation of the `wallet` on behalf of the transaction
+/// with `hash` and records its new `status`. Frozen funds are consumed if the mail item
+/// is accepted and returned otherwise.
+///
+/// Returns the asset and the amount of the preparation, or the stamp and its denomination.
+fn finish_preparation(
+    schema: &mut CurrencySchema<&mut Fork>,
+    wallet: Wallet,
+    status: u64,
+    hash: &Hash,
+) -> Result<(Hash, Amount), Error> {
+    let refund = status != MAIL_ACCEPTED;
+    let pub_key = *wallet.pub_key();
+    let asset_preparation = schema.pending_asset_preparation(&pub_key);
+    let stamp = schema.pending_stamp_preparation(&pub_key);
+    if let Some(preparation) = schema.pending_preparations().get(&pub_key) {
+        schema.asset_preparations_mut().remove(&preparation);
+        schema.mail_statuses_mut().put(&preparation, status);
+        if status == MAIL_ACCEPTED {
+            schema.accepted_preparations_mut().put(hash, preparation);
+        }
+    }
+    schema.pending_preparations_mut().remove(&pub_key);
+
+    if let Some(stamp) = stamp {
+        schema.increase_wallet_balance(wallet, Amount(0), hash, Amount(0))?;
+        let (id, denomination) = (*stamp.id(), stamp.denomination());
+        let stamp = if refund {
+            stamp.set_mail_item(&Hash::zero())
+        } else {
+            stamp.set_acceptance(hash)
+        };
+        schema.stamps_mut().put(&id, stamp);
+        Ok((id, denomination))
+    } else if let Some(preparation) = asset_preparation {
+        // The wallet balance is unchanged, the entry only records the end of the preparation.
+        schema.increase_wallet_balance(wallet, Amount(0), hash, Amount(0))?;
+        if refund {
+            schema.increase_asset_balance(&pub_key, preparation.asset(), preparation.amount())?;
+        }
+        Ok((*preparation.asset(), preparation.amount()))
+    } else {
+        let amount = wallet.freezed_balance();
+        let returned = if refund { amount } else { Amount(0) };
+        schema.increase_wallet_balance(wallet, returned, hash, Amount(0))?;
+        Ok((Hash::zero(), amount))
+    }
+}
+
 impl Transaction for MailPreparation {
     fn verify(&self) -> bool {
         self.verify_signature(self.pub_key())
     }
 
     fn execute(&self, fork: &mut Fork) -> ExecutionResult {
-        let time = TimeSchema::new(&fork)
-            .time()
-            .get();
-        let mut schema = CurrencySchema :: new(fork);
-        let pub_key = self.pub_key();
-        let amount = self.amount();
-        let hash = self.hash();
-        let sender = schema.wallet(pub_key).ok_or(Error :: SenderNotFound)?;
-        if sender.balance() < amount {
-            Err(Error::InsufficientCurrencyAmount)?;
-        }
-        schema.decrease_wallet_balance(sender, amount, &hash, amount);
-        let entry = TimestampEntry::new(&self.hash(), time.unwrap());
-        schema.add_timestamp(entry);
-        Ok(())
+        prepare_mail(fork, self.pub_key(), self.amount(), &self.hash())
     }
 }
 
@@ -248,9 +1026,7 @@ impl Transaction for MailAcceptance {
     }
 
     fn execute(&self, fork: &mut Fork) -> ExecutionResult {
-        let time = TimeSchema::new(&fork)
-            .time()
-            .get();
+        let time = current_time(fork)?;
         let mut schema = CurrencySchema :: new(fork);
         let sender_key = self.sender_key();
         let accept = self.accept();
@@ -258,16 +1034,17 @@ impl Transaction for MailAcceptance {
         if !schema.inspectors().contains(self.pub_key()) {
         	Err(Error::NotInspector)?
         }
-        let sender = schema.wallet(sender_key).ok_or(Error :: SenderNotFound)?;
-        if accept {
-            let freezed_balance = 0;
-            schema.decrease_wallet_balance(sender, freezed_balance, &hash, freezed_balance);
+        let sender = schema.forwarded_wallet(sender_key).ok_or(Error :: SenderNotFound)?;
+        let sender_key = *sender.pub_key();
+        let status = if accept { MAIL_ACCEPTED } else { MAIL_REJECTED };
+        let (asset, amount) = finish_preparation(&mut schema, sender, status, &hash)?;
+        let event = if accept {
+            PostEvent::MailAccepted { pub_key: sender_key, asset, amount }
         } else {
-            let amount = sender.freezed_balance();
-            let freezed_balance = 0;
-            schema.increase_wallet_balance(sender, amount, &hash, freezed_balance);
-        }
-        let entry = TimestampEntry::new(&self.hash(), time.unwrap());
+            PostEvent::MailRejected { pub_key: sender_key, asset, amount }
+        };
+        schema.emit_event(&hash, event);
+        let entry = TimestampEntry::new(&self.hash(), time);
         schema.add_timestamp(entry);
         Ok(())
     }
@@ -280,67 +1057,1309 @@ impl Transaction for Cancellation {
 
     fn execute(&self, fork: &mut Fork) -> ExecutionResult {
         let n = 100000;
-        let time = TimeSchema::new(&fork)
-            .time()
-            .get()
-            .unwrap();
+        let time = current_time(fork)?;
         let mut schema = CurrencySchema :: new(fork);
         let tx_hash = self.tx_hash();
         let hash = self.hash();
         if !schema.inspectors().contains(self.pub_key()) {
         	Err(Error::NotInspector)?
         }
-        let tx_time = schema.timestamps().get(&tx_hash).unwrap();
+        let tx_time = schema.timestamps().get(&tx_hash).ok_or(Error::TransactionNotFound)?;
         if time.timestamp() - tx_time < n {
-            let raw_tx = match schema.transactions().get(&tx_hash) {
-            	Some(x) => x,
-            	None => panic!("Transaction not found!"),
-            };
+            if schema.cancelled_transactions().contains(tx_hash) {
+                Err(Error::TransactionAlreadyCancelled)?
+            }
+            let raw_tx = schema.transactions().get(&tx_hash).ok_or(Error::TransactionNotFound)?;
+            if raw_tx.service_id() != POST_SERVICE_ID {
+                Err(Error::UnknownTransactionType)?
+            }
             let id = raw_tx.message_type();
             match id {
-                0 => {
-                	let transaction: Transfer = Message::from_raw(raw_tx.clone()).unwrap();
+                Transfer::MESSAGE_ID => {
+                	let transaction: Transfer = Message::from_raw(raw_tx.clone())
+                        .map_err(|_| Error::UnknownTransactionType)?;
 	                let from = transaction.from();
 	                let to = transaction.to();
 	                let amount = transaction.amount();
-	                let wallet_from = schema.wallet(&from).ok_or(Error :: SenderNotFound)?;
-	                let wallet_to = schema.wallet(to).ok_or(Error :: ReceiverNotFound)?;
-	                schema.decrease_wallet_balance(wallet_to, amount, &tx_hash, 0);
-	                schema.increase_wallet_balance(wallet_from, amount, &tx_hash, 0);
+	                let wallet_from = schema.forwarded_wallet(&from).ok_or(Error :: SenderNotFound)?;
+	                let wallet_to = schema.forwarded_wallet(to).ok_or(Error :: ReceiverNotFound)?;
+	                schema.decrease_wallet_balance(wallet_to, amount, &hash, Amount(0))?;
+	                schema.increase_wallet_balance(wallet_from, amount, &hash, Amount(0))?;
 	            },
-	            1 => {
-	            	let transaction: Issue = Message::from_raw(raw_tx.clone()).unwrap();
+                TransferWithReference::MESSAGE_ID => {
+                    let transaction: TransferWithReference =
+                        Message::from_raw(raw_tx.clone())
+                            .map_err(|_| Error::UnknownTransactionType)?;
+                    let amount = transaction.amount();
+                    let wallet_from = schema
+                        .forwarded_wallet(transaction.from())
+                        .ok_or(Error::SenderNotFound)?;
+                    let wallet_to = schema
+                        .forwarded_wallet(transaction.to())
+                        .ok_or(Error::ReceiverNotFound)?;
+                    schema.decrease_wallet_balance(wallet_to, amount, &hash, Amount(0))?;
+                    schema.increase_wallet_balance(wallet_from, amount, &hash, Amount(0))?;
+                },
+	            Issue::MESSAGE_ID => {
+	            	let transaction: Issue = Message::from_raw(raw_tx.clone())
+                        .map_err(|_| Error::UnknownTransactionType)?;
 	                let pub_key = transaction.pub_key();
 	                let amount = transaction.amount();
-	                let sender = schema.wallet(&pub_key).ok_or(Error :: ReceiverNotFound)?;
-	                schema.decrease_wallet_balance(sender, amount, &tx_hash, 0);
+	                let sender = schema.forwarded_wallet(&pub_key).ok_or(Error :: ReceiverNotFound)?;
+	                schema.decrease_wallet_balance(sender, amount, &hash, Amount(0))?;
 	              
 	            },
-	            3 => {
-	                let transaction: MailPreparation = Message::from_raw(raw_tx.clone()).unwrap();
+	            MailPreparation::MESSAGE_ID => {
+	                let transaction: MailPreparation = Message::from_raw(raw_tx.clone())
+                        .map_err(|_| Error::UnknownTransactionType)?;
 	                let pub_key = transaction.pub_key();
 	                let amount = transaction.amount();
-	                let sender = schema.wallet(&pub_key).ok_or(Error :: ReceiverNotFound)?;
-	                schema.increase_wallet_balance(sender, amount, &hash, 0);
-	               
+	                if schema.mail_statuses().get(tx_hash) != Some(MAIL_PENDING) {
+	                    Err(Error::MailItemNotPending)?
+	                }
+	                let sender = schema.forwarded_wallet(&pub_key).ok_or(Error :: ReceiverNotFound)?;
+	                let sender_key = *sender.pub_key();
+	                schema.increase_wallet_balance(sender, amount, &hash, Amount(0))?;
+	                if schema.pending_preparations().get(&sender_key) == Some(*tx_hash) {
+	                    schema.pending_preparations_mut().remove(&sender_key);
+	                }
+	                schema.mail_statuses_mut().put(tx_hash, MAIL_CANCELLED);
 	            },
-                4 => {
-                	let transaction: MailAcceptance = Message::from_raw(raw_tx.clone()).unwrap();
+                MailAcceptance::MESSAGE_ID => {
+                	let transaction: MailAcceptance = Message::from_raw(raw_tx.clone())
+                        .map_err(|_| Error::UnknownTransactionType)?;
                 	if transaction.accept() {
                     	let pub_key = transaction.sender_key();
-                    	let amount = transaction.amount();
-                    	let sender = schema.wallet(&pub_key).ok_or(Error :: ReceiverNotFound)?;
-                    	schema.increase_wallet_balance(sender, amount, &hash, 0);
+                    	let preparation = schema
+                    	    .accepted_preparations()
+                    	    .get(tx_hash)
+                    	    .ok_or(Error::AcceptanceNotCancellable)?;
+                    	let amount = postage_amount(&schema, &preparation)?;
+                    	let sender = schema
+                    	    .forwarded_wallet(&pub_key)
+                    	    .ok_or(Error::ReceiverNotFound)?;
+                    	schema.increase_wallet_balance(sender, amount, &hash, Amount(0))?;
+                    	schema.mail_statuses_mut().put(&preparation, MAIL_CANCELLED);
                     }
                     
                 },
-                _ => panic!("Transaction is not defined"),
+                IssueAsset::MESSAGE_ID => {
+                    let transaction: IssueAsset = Message::from_raw(raw_tx.clone())
+                        .map_err(|_| Error::UnknownTransactionType)?;
+                    let (asset, amount) = (transaction.asset(), transaction.amount());
+                    let wallet = schema
+                        .forwarded_wallet(transaction.pub_key())
+                        .ok_or(Error::ReceiverNotFound)?;
+                    if *asset == Hash::zero() {
+                        schema.decrease_wallet_balance(wallet, amount, &hash, Amount(0))?;
+                    } else {
+                        schema.decrease_asset_balance(wallet.pub_key(), asset, amount)?;
+                        schema.append_history(wallet, &hash);
+                    }
+                },
+                TransferAsset::MESSAGE_ID => {
+                    let transaction: TransferAsset = Message::from_raw(raw_tx.clone())
+                        .map_err(|_| Error::UnknownTransactionType)?;
+                    let (asset, amount) = (transaction.asset(), transaction.amount());
+                    let wallet_from = schema
+                        .forwarded_wallet(transaction.from())
+                        .ok_or(Error::SenderNotFound)?;
+                    let wallet_to = schema
+                        .forwarded_wallet(transaction.to())
+                        .ok_or(Error::ReceiverNotFound)?;
+                    if *asset == Hash::zero() {
+                        schema.decrease_wallet_balance(wallet_to, amount, &hash, Amount(0))?;
+                        schema.increase_wallet_balance(wallet_from, amount, &hash, Amount(0))?;
+                    } else {
+                        schema.decrease_asset_balance(wallet_to.pub_key(), asset, amount)?;
+                        schema.increase_asset_balance(wallet_from.pub_key(), asset, amount)?;
+                        schema.append_history(wallet_to, &hash);
+                        schema.append_history(wallet_from, &hash);
+                    }
+                },
+                AssetMailPreparation::MESSAGE_ID => {
+                    let transaction: AssetMailPreparation = Message::from_raw(raw_tx.clone())
+                        .map_err(|_| Error::UnknownTransactionType)?;
+                    let (asset, amount) = (transaction.asset(), transaction.amount());
+                    if schema.mail_statuses().get(tx_hash) != Some(MAIL_PENDING) {
+                        Err(Error::MailItemNotPending)?
+                    }
+                    let sender = schema
+                        .forwarded_wallet(transaction.pub_key())
+                        .ok_or(Error::ReceiverNotFound)?;
+                    let sender_key = *sender.pub_key();
+                    if *asset == Hash::zero() {
+                        schema.increase_wallet_balance(sender, amount, &hash, Amount(0))?;
+                    } else {
+                        schema.increase_asset_balance(&sender_key, asset, amount)?;
+                        schema.asset_preparations_mut().remove(tx_hash);
+                        schema.append_history(sender, &hash);
+                    }
+                    if schema.pending_preparations().get(&sender_key) == Some(*tx_hash) {
+                        schema.pending_preparations_mut().remove(&sender_key);
+                    }
+                    schema.mail_statuses_mut().put(tx_hash, MAIL_CANCELLED);
+                },
+                _ => Err(Error::UnknownTransactionType)?,
        		};
+       		schema.cancelled_transactions_mut().insert(*tx_hash);
        		let entry = TimestampEntry::new(&self.hash(), time);
         	schema.add_timestamp(entry);
+            let event = PostEvent::Cancelled {
+                inspector: *self.pub_key(),
+                tx_hash: *tx_hash,
+            };
+            schema.emit_event(&hash, event);
        	} else {
        		Err(Error::Timeisup)?;
        	}
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Returns the postage units paid by the mail preparation with `hash`. Preparations paid
+/// with registered assets or stamps have no postage to return.
+fn postage_amount(schema: &CurrencySchema<&mut Fork>, hash: &Hash) -> Result<Amount, Error> {
+    let raw_tx = schema.transactions().get(hash).ok_or(Error::TransactionNotFound)?;
+    match raw_tx.message_type() {
+        MailPreparation::MESSAGE_ID => {
+            let transaction: MailPreparation =
+                Message::from_raw(raw_tx).map_err(|_| Error::UnknownTransactionType)?;
+            Ok(transaction.amount())
+        }
+        AssetMailPreparation::MESSAGE_ID => {
+            let transaction: AssetMailPreparation =
+                Message::from_raw(raw_tx).map_err(|_| Error::UnknownTransactionType)?;
+            if *transaction.asset() != Hash::zero() {
+                Err(Error::AcceptanceNotCancellable)?
+            }
+            Ok(transaction.amount())
+        }
+        _ => Err(Error::AcceptanceNotCancellable),
+    }
+}
+
+/// Returns the payload of the rotation of `pub_key` to `new_pub_key` that is signed
+/// with the new secret key, see `RotateKey`.
+pub fn rotation_payload(pub_key: &PublicKey, new_pub_key: &PublicKey, seed: u64) -> Hash {
+    crypto::hash(&[pub_key.as_ref(), new_pub_key.as_ref(), seed.to_string().as_bytes()].concat())
+}
+
+impl Transaction for RotateKey {
+    fn verify(&self) -> bool {
+        let payload = rotation_payload(self.pub_key(), self.new_pub_key(), self.seed());
+        (self.pub_key() != self.new_pub_key())
+            && crypto::verify(self.new_key_signature(), payload.as_ref(), self.new_pub_key())
+            && self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+
+        let wallet = schema.wallet(self.pub_key()).ok_or(Error::SenderNotFound)?;
+        if schema.is_key_used(self.new_pub_key()) {
+            Err(Error::WalletAlreadyExists)?
+        }
+
+        schema.rotate_wallet_key(wallet, self.new_pub_key(), &hash);
+        schema.move_asset_balances(self.pub_key(), self.new_pub_key())?;
+        schema.move_stamps(self.pub_key(), self.new_pub_key());
+        let event = PostEvent::KeyRotated {
+            old_key: *self.pub_key(),
+            new_key: *self.new_pub_key(),
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+/// Changes the suspension status of `wallet` on behalf of the compliance `officer`.
+fn set_wallet_suspended(
+    fork: &mut Fork,
+    officer: &PublicKey,
+    wallet: &PublicKey,
+    hash: &Hash,
+    suspended: bool,
+) -> ExecutionResult {
+    let time = current_time(fork)?;
+    let mut schema = CurrencySchema::new(fork);
+
+    if !schema.compliance_officers().contains(officer) {
+        Err(Error::NotComplianceOfficer)?
+    }
+    // A suspended officer cannot act, in particular lift its own suspension.
+    if schema.wallet(officer).map_or(false, |officer| officer.suspended()) {
+        Err(Error::WalletSuspended)?
+    }
+    let wallet = schema.wallet(wallet).ok_or(Error::WalletNotFound)?;
+    let event = PostEvent::SuspensionChanged {
+        pub_key: *wallet.pub_key(),
+        suspended,
+    };
+    schema.set_wallet_suspended(wallet, suspended, hash);
+    schema.emit_event(hash, event);
+
+    let entry = TimestampEntry::new(hash, time);
+    schema.add_timestamp(entry);
+    Ok(())
+}
+
+impl Transaction for SuspendWallet {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        set_wallet_suspended(fork, self.pub_key(), self.wallet(), &self.hash(), true)
+    }
+}
+
+impl Transaction for UnsuspendWallet {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        set_wallet_suspended(fork, self.pub_key(), self.wallet(), &self.hash(), false)
+    }
+}
+
+impl Transaction for CloseWallet {
+    fn verify(&self) -> bool {
+        (self.pub_key() != self.beneficiary()) && self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let pub_key = self.pub_key();
+        let hash = self.hash();
+
+        let wallet = schema.wallet(pub_key).ok_or(Error::SenderNotFound)?;
+        let day = day_bucket(time);
+        check_outgoing(&schema, &wallet, wallet.balance(), day)?;
+        if wallet.freezed_balance() != Amount(0)
+            || schema.pending_asset_preparation(pub_key).is_some()
+            || schema.pending_stamp_preparation(pub_key).is_some()
+        {
+            Err(Error::OpenMailItems)?
+        }
+        let beneficiary = schema
+            .forwarded_wallet(self.beneficiary())
+            .ok_or(Error::ReceiverNotFound)?;
+        if beneficiary.pub_key() == pub_key {
+            Err(Error::ReceiverIsSender)?
+        }
+
+        // Pending scheduled transfers are cancelled, and their reserved funds are swept
+        // along with the balance.
+        let mut amount = wallet.balance();
+        let scheduled = schema.sender_scheduled_transfers(pub_key).iter().collect::<Vec<_>>();
+        for tx_hash in scheduled {
+            if let Some(pending) = schema.scheduled_transfers().get(&tx_hash) {
+                amount = amount.checked_add(pending.amount())?;
+                schema.scheduled_transfers_mut().remove(&tx_hash);
+                schema.remove_due(pending.execute_at(), &tx_hash);
+            }
+        }
+        schema.sender_scheduled_transfers_mut(pub_key).clear();
+
+        let freezed_balance = beneficiary.freezed_balance();
+        let event = PostEvent::WalletClosed {
+            pub_key: *pub_key,
+            beneficiary: *beneficiary.pub_key(),
+            amount,
+        };
+        let beneficiary_key = *beneficiary.pub_key();
+        schema.add_daily_outbound(pub_key, day, wallet.balance());
+        schema.close_wallet(wallet, &hash);
+        schema.increase_wallet_balance(beneficiary, amount, &hash, freezed_balance)?;
+        // Vouchers are not transferable, so they become void instead of being swept.
+        for balance in schema.void_vouchers(pub_key)? {
+            let event = PostEvent::VoucherVoided {
+                pub_key: *pub_key,
+                asset: *balance.asset(),
+                amount: balance.balance(),
+            };
+            schema.emit_event(&hash, event);
+        }
+        schema.move_asset_balances(pub_key, &beneficiary_key)?;
+        schema.move_stamps(pub_key, &beneficiary_key);
+        schema.emit_event(&hash, event);
+        for name in schema.release_names(pub_key) {
+            schema.emit_event(&hash, PostEvent::NameReleased { name });
+        }
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for SetWalletLimits {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let hash = self.hash();
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        if !schema.compliance_officers().contains(self.pub_key()) {
+            Err(Error::NotComplianceOfficer)?
+        }
+        if schema.wallet(self.pub_key()).map_or(false, |officer| officer.suspended()) {
+            Err(Error::WalletSuspended)?
+        }
+        if !schema.wallets().contains(self.wallet()) {
+            Err(Error::WalletNotFound)?
+        }
+        let limits = Limits::new(self.max_transfer(), self.daily_outbound(), self.daily_issue());
+        schema.wallet_limits_mut().put(self.wallet(), limits);
+        let event = PostEvent::WalletLimitsSet { pub_key: *self.wallet() };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for SetRoleLimits {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let hash = self.hash();
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        if !schema.compliance_officers().contains(self.pub_key()) {
+            Err(Error::NotComplianceOfficer)?
+        }
+        if schema.wallet(self.pub_key()).map_or(false, |officer| officer.suspended()) {
+            Err(Error::WalletSuspended)?
+        }
+        let limits = Limits::new(self.max_transfer(), self.daily_outbound(), self.daily_issue());
+        schema.role_limits_mut().put(&self.role(), limits);
+        schema.emit_event(&hash, PostEvent::RoleLimitsSet { role: self.role() });
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for ScheduledTransfer {
+    fn verify(&self) -> bool {
+        (self.from() != self.to()) && self.verify_signature(self.from())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let from = self.from();
+        let hash = self.hash();
+        let amount = self.amount();
+
+        let sender = schema.wallet(from).ok_or(Error::SenderNotFound)?;
+        let receiver = schema.forwarded_wallet(self.to()).ok_or(Error::ReceiverNotFound)?;
+        if receiver.pub_key() == from {
+            Err(Error::ReceiverIsSender)?
+        }
+        let day = day_bucket(time);
+        check_outgoing(&schema, &sender, amount, day)?;
+
+        let freezed_balance = sender.freezed_balance();
+        schema.decrease_wallet_balance(sender, amount, &hash, freezed_balance)?;
+        schema.add_daily_outbound(from, day, amount);
+        let pending = PendingTransfer::new(&hash, from, self.to(), amount, self.execute_at());
+        schema.scheduled_transfers_mut().put(&hash, pending);
+        schema.sender_scheduled_transfers_mut(from).insert(hash);
+        schema.add_due(self.execute_at(), &hash);
+        let event = PostEvent::TransferScheduled {
+            from: *from,
+            to: *self.to(),
+            amount,
+            execute_at: self.execute_at(),
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for ExecuteScheduled {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        if !is_validator(fork, self.pub_key()) {
+            Err(Error::NotValidator)?
+        }
+
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+        let pending = schema
+            .scheduled_transfers()
+            .get(self.tx_hash())
+            .ok_or(Error::ScheduledTransferNotFound)?;
+        if time < pending.execute_at() {
+            Err(Error::ScheduledTimeNotReached)?
+        }
+
+        // Reserved funds are returned to the sender if the receiver has closed its wallet,
+        // or if the sender is suspended, as its outgoing transactions fail.
+        // Closing the sender cancels its scheduled transfers, so the sender is still open.
+        let sender = schema.forwarded_wallet(pending.from());
+        let suspended = sender.as_ref().map_or(false, Wallet::suspended);
+        let (receiver, refund) = match schema.forwarded_wallet(pending.to()) {
+            Some(receiver) if !suspended => (receiver, false),
+            _ => (sender.ok_or(Error::ReceiverNotFound)?, true),
+        };
+        schema.scheduled_transfers_mut().remove(self.tx_hash());
+        schema.remove_due(pending.execute_at(), self.tx_hash());
+        if let Some(sender) = schema.forwarded_wallet(pending.from()) {
+            schema
+                .sender_scheduled_transfers_mut(sender.pub_key())
+                .remove(self.tx_hash());
+        }
+
+        let event = if refund {
+            PostEvent::TransferRefunded {
+                transfer: *self.tx_hash(),
+                pub_key: *receiver.pub_key(),
+                amount: pending.amount(),
+            }
+        } else {
+            PostEvent::Transferred {
+                from: *pending.from(),
+                to: *receiver.pub_key(),
+                asset: Hash::zero(),
+                amount: pending.amount(),
+            }
+        };
+        let amount = pending.amount();
+        let freezed_balance = receiver.freezed_balance();
+        schema.increase_wallet_balance(receiver, amount, &hash, freezed_balance)?;
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for CreateStandingOrder {
+    fn verify(&self) -> bool {
+        (self.payer() != self.payee())
+            && self.amount() > Amount(0)
+            && self.period() > 0
+            && self.start() <= self.end()
+            && self.verify_signature(self.payer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        if self.start() < time {
+            Err(Error::TimeInPast)?
+        }
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+
+        schema.wallet(self.payer()).ok_or(Error::SenderNotFound)?;
+        schema.forwarded_wallet(self.payee()).ok_or(Error::ReceiverNotFound)?;
+
+        let order = StandingOrder::new(
+            &hash,
+            self.payer(),
+            self.payee(),
+            self.amount(),
+            self.period(),
+            self.start(),
+            self.end(),
+            0,
+            0,
+        );
+        schema.standing_orders_mut().put(&hash, order);
+        schema.add_due(self.start(), &hash);
+        schema.emit_event(&hash, PostEvent::StandingOrderCreated { order: hash });
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for CancelStandingOrder {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+
+        let order = schema
+            .standing_orders()
+            .get(self.order())
+            .ok_or(Error::StandingOrderNotFound)?;
+        let payer = schema.forwarded_wallet(order.payer());
+        if payer.map_or(true, |payer| payer.pub_key() != self.pub_key()) {
+            Err(Error::NotStandingOrderPayer)?
+        }
+        schema.standing_orders_mut().remove(self.order());
+        schema.remove_due(order.next_payment(), self.order());
+        let event = PostEvent::StandingOrderCancelled { order: *self.order() };
+        schema.emit_event(&self.hash(), event);
+
+        let entry = TimestampEntry::new(&self.hash(), time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for ExecuteStandingOrder {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        if !is_validator(fork, self.pub_key()) {
+            Err(Error::NotValidator)?
+        }
+
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+        let order = schema
+            .standing_orders()
+            .get(self.order())
+            .ok_or(Error::StandingOrderNotFound)?;
+        if time < order.next_payment() {
+            Err(Error::ScheduledTimeNotReached)?
+        }
+        schema.remove_due(order.next_payment(), self.order());
+
+        let payer = schema.forwarded_wallet(order.payer());
+        let payee = schema.forwarded_wallet(order.payee());
+        let day = day_bucket(time);
+        // Payments that cannot be made are skipped rather than retried every block.
+        let paid = match (payer, payee) {
+            (Some(payer), Some(payee)) => {
+                if payer.pub_key() != payee.pub_key()
+                    && check_outgoing(&schema, &payer, order.amount(), day).is_ok()
+                {
+                    let payment = StandingOrderPayment::new(
+                        self.order(),
+                        &hash,
+                        payer.pub_key(),
+                        payee.pub_key(),
+                        order.amount(),
+                        order.payments() + 1,
+                    );
+                    let payment_hash = payment.hash();
+                    schema.standing_order_payments_mut().put(&payment_hash, payment);
+
+                    let payer_key = *payer.pub_key();
+                    let event = PostEvent::Transferred {
+                        from: payer_key,
+                        to: *payee.pub_key(),
+                        asset: Hash::zero(),
+                        amount: order.amount(),
+                    };
+                    let payer_freezed_balance = payer.freezed_balance();
+                    let payee_freezed_balance = payee.freezed_balance();
+                    schema.decrease_wallet_balance(
+                        payer,
+                        order.amount(),
+                        &payment_hash,
+                        payer_freezed_balance,
+                    )?;
+                    schema.increase_wallet_balance(
+                        payee,
+                        order.amount(),
+                        &payment_hash,
+                        payee_freezed_balance,
+                    )?;
+                    schema.add_daily_outbound(&payer_key, day, order.amount());
+                    schema.add_timestamp(TimestampEntry::new(&payment_hash, time));
+                    schema.emit_event(&hash, event);
+                    true
+                } else {
+                    false
+                }
+            }
+            (None, _) => {
+                // Closed payer cannot make any more payments.
+                schema.standing_orders_mut().remove(self.order());
+                schema.add_timestamp(TimestampEntry::new(&hash, time));
+                return Ok(());
+            }
+            _ => false,
+        };
+
+        let order = order.advance(paid);
+        if order.is_finished() {
+            schema.standing_orders_mut().remove(self.order());
+        } else {
+            schema.add_due(order.next_payment(), self.order());
+            schema.standing_orders_mut().put(self.order(), order);
+        }
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for CreateInvoice {
+    fn verify(&self) -> bool {
+        (self.payee() != self.payer()) && self.verify_signature(self.payee())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+
+        schema.wallet(self.payee()).ok_or(Error::SenderNotFound)?;
+        let payer = schema.forwarded_wallet(self.payer()).ok_or(Error::ReceiverNotFound)?;
+
+        // The invoice is listed under the current key of the payer.
+        let invoice = Invoice::new(
+            &hash,
+            self.payee(),
+            payer.pub_key(),
+            self.amount(),
+            self.reference(),
+            self.expires_at(),
+            false,
+        );
+        schema.add_invoice(invoice);
+        schema.emit_event(&hash, PostEvent::InvoiceCreated { invoice: hash });
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for PayInvoice {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.payer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+
+        let invoice = schema
+            .invoices()
+            .get(self.invoice())
+            .ok_or(Error::InvoiceNotFound)?;
+        if invoice.paid() {
+            Err(Error::InvoiceAlreadyPaid)?
+        }
+        if time > invoice.expires_at() {
+            Err(Error::InvoiceExpired)?
+        }
+
+        let payer = schema.wallet(self.payer()).ok_or(Error::SenderNotFound)?;
+        let invoice_payer = schema.forwarded_wallet(invoice.payer());
+        if invoice_payer.map_or(true, |wallet| wallet.pub_key() != self.payer()) {
+            Err(Error::NotInvoicePayer)?
+        }
+        let payee = schema
+            .forwarded_wallet(invoice.payee())
+            .ok_or(Error::ReceiverNotFound)?;
+        if payee.pub_key() == self.payer() {
+            Err(Error::ReceiverIsSender)?
+        }
+        let amount = invoice.amount();
+        let day = day_bucket(time);
+        check_outgoing(&schema, &payer, amount, day)?;
+
+        let payer_freezed_balance = payer.freezed_balance();
+        let payee_freezed_balance = payee.freezed_balance();
+        let event = PostEvent::Transferred {
+            from: *self.payer(),
+            to: *payee.pub_key(),
+            asset: Hash::zero(),
+            amount,
+        };
+        schema.decrease_wallet_balance(payer, amount, &hash, payer_freezed_balance)?;
+        schema.increase_wallet_balance(payee, amount, &hash, payee_freezed_balance)?;
+        schema.add_daily_outbound(self.payer(), day, amount);
+        schema.invoices_mut().put(self.invoice(), invoice.set_paid());
+        schema.emit_event(&hash, event);
+        schema.emit_event(&hash, PostEvent::InvoicePaid { invoice: *self.invoice() });
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for TransferWithReference {
+    fn verify(&self) -> bool {
+        (self.from() != self.to()) && self.verify_signature(self.from())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let hash = self.hash();
+        transfer(fork, self.from(), self.to(), self.amount(), &hash)?;
+
+        // Transfers without a reference are not listed.
+        if !self.reference().is_empty() {
+            let mut schema = CurrencySchema::new(fork);
+            schema.transfers_by_reference_mut(self.reference()).push(hash);
+        }
+        Ok(())
+    }
+}
+
+impl Transaction for RegisterName {
+    fn verify(&self) -> bool {
+        normalize_name(self.name()).is_some() && self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = CurrencySchema::new(fork);
+        let name = normalize_name(self.name()).ok_or(Error::InvalidName)?;
+
+        schema.wallet(self.pub_key()).ok_or(Error::WalletNotFound)?;
+        if schema.name_record(&name).is_some() {
+            Err(Error::NameAlreadyTaken)?
+        }
+        if schema.wallet_names(self.pub_key()).iter().count() >= MAX_WALLET_NAMES {
+            Err(Error::TooManyNames)?
+        }
+
+        let record = NameRecord::new(&name, self.pub_key());
+        let name_hash = crypto::hash(name.as_bytes());
+        schema.names_mut().put(&name_hash, record);
+        schema.wallet_names_mut(self.pub_key()).insert(name_hash);
+        let event = PostEvent::NameRegistered {
+            name,
+            pub_key: *self.pub_key(),
+        };
+        schema.emit_event(&self.hash(), event);
+        Ok(())
+    }
+}
+
+impl Transaction for ReleaseName {
+    fn verify(&self) -> bool {
+        normalize_name(self.name()).is_some() && self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = CurrencySchema::new(fork);
+        let name = normalize_name(self.name()).ok_or(Error::InvalidName)?;
+
+        let record = schema.name_record(&name).ok_or(Error::NameNotOwned)?;
+        let owner = schema.forwarded_wallet(record.pub_key());
+        if owner.map_or(true, |wallet| wallet.pub_key() != self.pub_key()) {
+            Err(Error::NameNotOwned)?
+        }
+
+        let name_hash = crypto::hash(name.as_bytes());
+        schema.names_mut().remove(&name_hash);
+        schema.wallet_names_mut(self.pub_key()).remove(&name_hash);
+        schema.emit_event(&self.hash(), PostEvent::NameReleased { name });
+        Ok(())
+    }
+}
+
+impl Transaction for ExpirePreparation {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        if !is_validator(fork, self.pub_key()) {
+            Err(Error::NotValidator)?
+        }
+
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+        let preparation = schema
+            .pending_preparations()
+            .get(self.wallet())
+            .ok_or(Error::PreparationNotFound)?;
+        let prepared_at = schema
+            .timestamps()
+            .get(&preparation)
+            .ok_or(Error::PreparationNotFound)?;
+        if time.timestamp() - prepared_at < PREPARATION_TTL {
+            Err(Error::PreparationNotExpired)?
+        }
+
+        let wallet = schema.wallet(self.wallet()).ok_or(Error::WalletNotFound)?;
+        let (asset, amount) = finish_preparation(&mut schema, wallet, MAIL_EXPIRED, &hash)?;
+        let event = PostEvent::MailExpired {
+            pub_key: *self.wallet(),
+            asset,
+            amount,
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for CreateAsset {
+    fn verify(&self) -> bool {
+        !self.name().trim().is_empty() && self.verify_signature(self.issuer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+
+        if !schema.issuers().contains(self.issuer()) {
+            Err(Error::NotIssuer)?
+        }
+
+        let asset = Asset::new(&hash, self.name(), self.issuer());
+        schema.assets_mut().put(&hash, asset);
+        let event = PostEvent::AssetCreated {
+            asset: hash,
+            issuer: *self.issuer(),
+            name: self.name().to_owned(),
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for IssueAsset {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.issuer_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let hash = self.hash();
+        if *self.asset() == Hash::zero() {
+            return issue(fork, self.issuer_key(), self.pub_key(), self.amount(), &hash);
+        }
+
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        if !schema.issuers().contains(self.issuer_key()) {
+            Err(Error::NotIssuer)?
+        }
+        let asset = schema.assets().get(self.asset()).ok_or(Error::AssetNotFound)?;
+        // The asset issuer may have rotated the key since the asset was registered.
+        let asset_issuer = schema.forwarded_wallet(asset.issuer());
+        if asset_issuer.map_or(true, |wallet| wallet.pub_key() != self.issuer_key()) {
+            Err(Error::NotAssetIssuer)?
+        }
+        if let Some(voucher) = schema.vouchers().get(self.asset()) {
+            if voucher.is_expired(time) {
+                Err(Error::VoucherExpired)?
+            }
+        }
+        let wallet = schema
+            .forwarded_wallet(self.pub_key())
+            .ok_or(Error::ReceiverNotFound)?;
+
+        schema.increase_asset_balance(wallet.pub_key(), self.asset(), self.amount())?;
+        let event = PostEvent::Issued {
+            issuer: *self.issuer_key(),
+            pub_key: *wallet.pub_key(),
+            asset: *self.asset(),
+            amount: self.amount(),
+        };
+        schema.append_history(wallet, &hash);
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for TransferAsset {
+    fn verify(&self) -> bool {
+        (self.from() != self.to()) && self.verify_signature(self.from())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let hash = self.hash();
+        if *self.asset() == Hash::zero() {
+            return transfer(fork, self.from(), self.to(), self.amount(), &hash);
+        }
+
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let sender = schema.wallet(self.from()).ok_or(Error::SenderNotFound)?;
+        let receiver = schema
+            .forwarded_wallet(self.to())
+            .ok_or(Error::ReceiverNotFound)?;
+        if receiver.pub_key() == self.from() {
+            Err(Error::ReceiverIsSender)?
+        }
+        if sender.suspended() {
+            Err(Error::WalletSuspended)?
+        }
+        if !schema.assets().contains(self.asset()) {
+            Err(Error::AssetNotFound)?
+        }
+        if schema.vouchers().contains(self.asset()) {
+            Err(Error::VoucherNotTransferable)?
+        }
+        let amount = self.amount();
+        schema.decrease_asset_balance(self.from(), self.asset(), amount)?;
+        schema.increase_asset_balance(receiver.pub_key(), self.asset(), amount)?;
+        let event = PostEvent::Transferred {
+            from: *self.from(),
+            to: *receiver.pub_key(),
+            asset: *self.asset(),
+            amount,
+        };
+        schema.append_history(sender, &hash);
+        schema.append_history(receiver, &hash);
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for AssetMailPreparation {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let hash = self.hash();
+        if *self.asset() == Hash::zero() {
+            return prepare_mail(fork, self.pub_key(), self.amount(), &hash);
+        }
+
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let pub_key = self.pub_key();
+        let sender = schema.wallet(pub_key).ok_or(Error::SenderNotFound)?;
+        if sender.suspended() {
+            Err(Error::WalletSuspended)?
+        }
+        // A wallet has at most one pending preparation, so that its acceptance is unambiguous.
+        if sender.freezed_balance() != Amount(0)
+            || schema.pending_preparations().contains(pub_key)
+        {
+            Err(Error::OpenMailItems)?
+        }
+        if !schema.assets().contains(self.asset()) {
+            Err(Error::AssetNotFound)?
+        }
+        if let Some(voucher) = schema.vouchers().get(self.asset()) {
+            if voucher.is_expired(time) {
+                Err(Error::VoucherExpired)?
+            }
+        }
+
+        schema.decrease_asset_balance(pub_key, self.asset(), self.amount())?;
+        let preparation = AssetPreparation::new(self.asset(), self.amount());
+        schema.asset_preparations_mut().put(&hash, preparation);
+        schema.pending_preparations_mut().put(pub_key, hash);
+        schema.mail_statuses_mut().put(&hash, MAIL_PENDING);
+        schema.append_history(sender, &hash);
+        let event = PostEvent::MailPrepared {
+            pub_key: *pub_key,
+            asset: *self.asset(),
+            amount: self.amount(),
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for CreateVoucher {
+    fn verify(&self) -> bool {
+        !self.name().trim().is_empty() && self.verify_signature(self.issuer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        if self.expires_at() <= time {
+            Err(Error::TimeInPast)?
+        }
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+
+        if !schema.issuers().contains(self.issuer()) {
+            Err(Error::NotIssuer)?
+        }
+
+        let asset = Asset::new(&hash, self.name(), self.issuer());
+        schema.assets_mut().put(&hash, asset);
+        let voucher = Voucher::new(&hash, self.expires_at());
+        schema.vouchers_mut().put(&hash, voucher);
+        let event = PostEvent::AssetCreated {
+            asset: hash,
+            issuer: *self.issuer(),
+            name: self.name().to_owned(),
+        };
+        schema.emit_event(&hash, event);
+        let event = PostEvent::VoucherCreated {
+            asset: hash,
+            expires_at: self.expires_at(),
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for MintStamps {
+    fn verify(&self) -> bool {
+        self.count() > 0
+            && self.count() <= MAX_MINT_BATCH
+            && self.first_number().checked_add(self.count()).is_some()
+            && !self.series().trim().is_empty()
+            && self.verify_signature(self.issuer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+
+        if !schema.issuers().contains(self.issuer()) {
+            Err(Error::NotIssuer)?
+        }
+        let owner = schema
+            .forwarded_wallet(self.owner())
+            .ok_or(Error::ReceiverNotFound)?;
+
+        for number in self.first_number()..self.first_number() + self.count() {
+            let id = stamp_id(self.issuer(), self.series(), number);
+            if schema.stamps().contains(&id) {
+                Err(Error::StampAlreadyExists)?
+            }
+            let stamp = Stamp::new(
+                &id,
+                self.series(),
+                number,
+                self.denomination(),
+                self.artwork(),
+                self.issuer(),
+                owner.pub_key(),
+                &Hash::zero(),
+                &Hash::zero(),
+            );
+            schema.stamps_mut().put(&id, stamp);
+            schema.owned_stamps_mut(owner.pub_key()).insert(id);
+        }
+        let event = PostEvent::StampsMinted {
+            issuer: *self.issuer(),
+            owner: *owner.pub_key(),
+            series: self.series().to_owned(),
+            first_number: self.first_number(),
+            count: self.count(),
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for TransferStamp {
+    fn verify(&self) -> bool {
+        (self.from() != self.to()) && self.verify_signature(self.from())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+
+        let sender = schema.wallet(self.from()).ok_or(Error::SenderNotFound)?;
+        if sender.suspended() {
+            Err(Error::WalletSuspended)?
+        }
+        let stamp = schema.stamps().get(self.stamp()).ok_or(Error::StampNotFound)?;
+        if stamp.owner() != self.from() {
+            Err(Error::NotStampOwner)?
+        }
+        if stamp.is_used() {
+            Err(Error::StampAlreadyUsed)?
+        }
+        let receiver = schema
+            .forwarded_wallet(self.to())
+            .ok_or(Error::ReceiverNotFound)?;
+        if receiver.pub_key() == self.from() {
+            Err(Error::ReceiverIsSender)?
+        }
+
+        schema.set_stamp_owner(stamp, receiver.pub_key());
+        let event = PostEvent::StampTransferred {
+            stamp: *self.stamp(),
+            from: *self.from(),
+            to: *receiver.pub_key(),
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for StampMailPreparation {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let pub_key = self.pub_key();
+        let hash = self.hash();
+
+        let sender = schema.wallet(pub_key).ok_or(Error::SenderNotFound)?;
+        if sender.suspended() {
+            Err(Error::WalletSuspended)?
+        }
+        // A wallet has at most one pending preparation, see `AssetMailPreparation`.
+        if sender.freezed_balance() != Amount(0)
+            || schema.pending_preparations().contains(pub_key)
+        {
+            Err(Error::OpenMailItems)?
+        }
+        let stamp = schema.stamps().get(self.stamp()).ok_or(Error::StampNotFound)?;
+        if stamp.owner() != pub_key {
+            Err(Error::NotStampOwner)?
+        }
+        if stamp.is_used() {
+            Err(Error::StampAlreadyUsed)?
+        }
+
+        let denomination = stamp.denomination();
+        schema.stamps_mut().put(self.stamp(), stamp.set_mail_item(&hash));
+        schema.stamp_preparations_mut().put(&hash, *self.stamp());
+        schema.pending_preparations_mut().put(pub_key, hash);
+        schema.mail_statuses_mut().put(&hash, MAIL_PENDING);
+        let event = PostEvent::MailPrepared {
+            pub_key: *pub_key,
+            asset: *self.stamp(),
+            amount: denomination,
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for RedeemStamp {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+
+        if !schema.sorting_machines().contains(self.pub_key()) {
+            Err(Error::NotSortingMachine)?
+        }
+        let stamp = schema.stamps().get(self.stamp()).ok_or(Error::StampNotFound)?;
+        if !stamp.is_accepted() {
+            Err(Error::MailItemNotAccepted)?
+        }
+        if schema.redemptions().contains(self.stamp()) {
+            Err(Error::StampAlreadyRedeemed)?
+        }
+
+        let redemption = Redemption::new(self.stamp(), stamp.mail_item(), self.pub_key(), time);
+        schema.redemptions_mut().put(self.stamp(), redemption);
+        let event = PostEvent::StampRedeemed {
+            stamp: *self.stamp(),
+            mail_item: *stamp.mail_item(),
+            machine: *self.pub_key(),
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for AssignRole {
+    fn verify(&self) -> bool {
+        (self.role() == 3 || self.role() == 4) && self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let time = current_time(fork)?;
+        if !is_validator(fork, self.pub_key()) {
+            Err(Error::NotValidator)?
+        }
+
+        let mut schema = CurrencySchema::new(fork);
+        let hash = self.hash();
+        let wallet = schema
+            .forwarded_wallet(self.wallet())
+            .ok_or(Error::WalletNotFound)?;
+        let pub_key = *wallet.pub_key();
+        schema.add_compliance_officer(&pub_key, self.role());
+        schema.add_sorting_machine(&pub_key, self.role());
+        let event = PostEvent::RoleAssigned {
+            pub_key,
+            role: self.role(),
+        };
+        schema.emit_event(&hash, event);
+
+        let entry = TimestampEntry::new(&hash, time);
+        schema.add_timestamp(entry);
+        Ok(())
+    }
+}
+
+impl Transaction for IndexTimestamps {
+    fn verify(&self) -> bool {
+        self.count() > 0 && self.count() <= MAX_INDEX_BATCH && self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        if !is_validator(fork, self.pub_key()) {
+            Err(Error::NotValidator)?
+        }
+
+        let mut schema = CurrencySchema::new(fork);
+        schema.backfill_time_index(self.count());
+        Ok(())
+    }
+}
@@ -17,21 +17,29 @@
 
 use exonum::crypto::{Hash, PublicKey};
 
+use amount::Amount;
+
 encoding_struct! {
     /// Wallet information stored in the database.
     struct Wallet {
         pub_key:            &PublicKey,
         name:               &str,
-        balance:            u64,
+        balance:            Amount,
         history_len:        u64,
         history_hash:       &Hash,
-        freezed_balance:    u64,
+        freezed_balance:    Amount,
+        suspended:          bool,
     }
 }
 
 impl Wallet {
     /// Returns a copy of this wallet with updated balance.
-    pub fn set_balance(self, balance: u64, history_hash: &Hash, freezed_balance: u64) -> Self {
+    pub fn set_balance(
+        self,
+        balance: Amount,
+        history_hash: &Hash,
+        freezed_balance: Amount,
+    ) -> Self {
         Self::new(
             self.pub_key(),
             self.name(),
@@ -39,6 +47,20 @@ impl Wallet {
             self.history_len() + 1,
             history_hash,
             freezed_balance,
+            self.suspended(),
+        )
+    }
+
+    /// Returns a copy of this wallet with updated suspension status.
+    pub fn set_suspended(self, suspended: bool, history_hash: &Hash) -> Self {
+        Self::new(
+            self.pub_key(),
+            self.name(),
+            self.balance(),
+            self.history_len() + 1,
+            history_hash,
+            self.freezed_balance(),
+            suspended,
         )
     }
 }
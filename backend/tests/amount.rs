@@ -0,0 +1,132 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Property tests of the checked amount arithmetic and of balances kept by the service.
+
+extern crate exonum;
+extern crate exonum_russian_post as cryptocurrency;
+extern crate exonum_testkit;
+extern crate exonum_time;
+#[macro_use]
+extern crate proptest;
+
+use exonum::{
+    blockchain::Transaction, crypto::{self, PublicKey, SecretKey}, helpers::Height,
+};
+use exonum_testkit::{TestKit, TestKitBuilder};
+use exonum_time::{time_provider::MockTimeProvider, TimeService};
+use proptest::prelude::*;
+
+use cryptocurrency::{
+    amount::Amount, transactions::{CreateWallet, Error, Transfer}, CurrencySchema,
+    CurrencyService,
+};
+
+use std::time::SystemTime;
+
+/// Number of wallets taking part in generated transfers.
+const WALLETS: usize = 4;
+/// Balance of a new wallet.
+const INITIAL_BALANCE: u64 = 100;
+
+proptest! {
+    #[test]
+    fn checked_add_never_wraps(a in any::<u64>(), b in any::<u64>()) {
+        match Amount(a).checked_add(Amount(b)) {
+            Ok(sum) => prop_assert_eq!(u128::from(sum.0), u128::from(a) + u128::from(b)),
+            Err(e) => {
+                prop_assert_eq!(e, Error::BalanceOverflow);
+                prop_assert!(a.checked_add(b).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn checked_sub_never_goes_negative(a in any::<u64>(), b in any::<u64>()) {
+        match Amount(a).checked_sub(Amount(b)) {
+            Ok(difference) => {
+                prop_assert!(b <= a);
+                prop_assert_eq!(difference.0 + b, a);
+            }
+            Err(e) => {
+                prop_assert_eq!(e, Error::InsufficientCurrencyAmount);
+                prop_assert!(b > a);
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    /// Transfers executed by the service never make a balance negative and preserve
+    /// the total supply: a transfer exceeding the balance fails and changes nothing.
+    #[test]
+    fn transfers_preserve_supply(
+        transfers in prop::collection::vec(
+            (0..WALLETS, 0..WALLETS, 0..2 * INITIAL_BALANCE),
+            1..20,
+        ),
+    ) {
+        let (mut testkit, keys) = create_wallets();
+        let mut expected = vec![Amount(INITIAL_BALANCE); WALLETS];
+        for (seed, (from, to, amount)) in transfers.into_iter().enumerate() {
+            // Transfers to oneself don't pass verification.
+            if from == to {
+                continue;
+            }
+            let (from_key, from_secret) = &keys[from];
+            let tx = Transfer::new(from_key, &keys[to].0, Amount(amount), seed as u64, from_secret);
+            testkit.create_block_with_transactions(vec![Box::new(tx) as Box<dyn Transaction>]);
+
+            // The model applies the same checked arithmetic, a failed transfer changes nothing.
+            if let Ok(balance) = expected[from].checked_sub(Amount(amount)) {
+                expected[from] = balance;
+                expected[to] = expected[to].checked_add(Amount(amount)).unwrap();
+            }
+        }
+
+        let snapshot = testkit.snapshot();
+        let schema = CurrencySchema::new(&snapshot);
+        let balances = keys
+            .iter()
+            .map(|(pub_key, _)| schema.wallet(pub_key).unwrap().balance())
+            .collect::<Vec<_>>();
+        prop_assert_eq!(&balances, &expected);
+        let total = balances.iter().map(|balance| balance.0).sum::<u64>();
+        prop_assert_eq!(total, INITIAL_BALANCE * WALLETS as u64);
+    }
+}
+
+/// Creates a testkit with `WALLETS` wallets and returns their keys.
+fn create_wallets() -> (TestKit, Vec<(PublicKey, SecretKey)>) {
+    let mock_provider = MockTimeProvider::new(SystemTime::now().into());
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(CurrencyService::default())
+        .with_service(TimeService::with_provider(mock_provider))
+        .create();
+    testkit.create_blocks_until(Height(2));
+
+    let keys = (0..WALLETS).map(|_| crypto::gen_keypair()).collect::<Vec<_>>();
+    let txs = keys
+        .iter()
+        .enumerate()
+        .map(|(i, (pub_key, secret_key))| {
+            let tx = CreateWallet::new(pub_key, &format!("Wallet {}", i), 0, secret_key);
+            Box::new(tx) as Box<dyn Transaction>
+        })
+        .collect::<Vec<_>>();
+    testkit.create_block_with_transactions(txs);
+    (testkit, keys)
+}
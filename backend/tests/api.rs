@@ -18,30 +18,55 @@
 //! Note how API tests predominantly use `TestKitApi` to send transactions and make assertions
 //! about the storage state.
 
+extern crate chrono;
 extern crate exonum;
 extern crate exonum_russian_post as cryptocurrency;
 extern crate exonum_testkit;
 extern crate exonum_time;
+extern crate serde;
 #[macro_use]
 extern crate serde_json;
 
+use chrono::{Duration, TimeZone, Utc};
 use exonum::{
-    api::node::public::explorer::TransactionQuery,
-    crypto::{self, CryptoHash, Hash, PublicKey, SecretKey}, 
-    helpers::Height,
+    api::node::public::explorer::TransactionQuery, blockchain::Transaction,
+    crypto::{self, CryptoHash, Hash, PublicKey, SecretKey}, helpers::Height, messages::Message,
 };
 use exonum_testkit::{ApiKind, TestKit, TestKitApi, TestKitBuilder};
-use exonum_time::{time_provider::MockTimeProvider, TimeService};
+use exonum_time::{schema::TimeSchema, time_provider::MockTimeProvider, TimeService};
 // Import data types used in tests from the crate where the service is defined.
 use cryptocurrency::{
-    api::{WalletInfo, WalletQuery}, transactions::{CreateWallet, Transfer, Issue, MailAcceptance, MailPreparation, Cancellation}, 
+    amount::Amount,
+    api::{
+        AccountStatement, BalanceAtQuery, EventLog, EventLogQuery, EventsQuery,
+        ExpirePreparationsQuery, HistoricalBalance, IndexTimestampsQuery, MailItemQuery, MailToken,
+        NameLookup, NameQuery, Page, PageQuery, ReferenceQuery, RolesDump, ServiceHealth,
+        StampQuery, StampVerification, StatementQuery, TimeRangeQuery, TransactionResponse,
+        WalletAssets, WalletEvents, WalletInfo, WalletQuery, WalletStamps,
+    },
+    config::{ServiceConfig, TimePolicy}, events::PostEvent, export::{self, ExportFormat, Statement},
+    notifier::{BlockNotifier, MAX_WAITERS},
+    schema::{
+        balance_record_key, hour_bucket, normalize_name, stamp_id, Asset, Invoice, TimeIndexEntry,
+        MAIL_ACCEPTED, MAIL_PENDING, MAX_WALLET_NAMES,
+    },
+    token::{base45_decode, base45_encode, PostageToken, TokenError},
+    transactions::{
+        rotation_payload, AssetMailPreparation, AssignRole, CancelStandingOrder, Cancellation,
+        CloseWallet, CreateAsset, CreateInvoice, CreateStandingOrder, CreateVoucher, CreateWallet,
+        ErrorDescription, ExecuteScheduled, ExpirePreparation, Issue, IssueAsset, MailAcceptance,
+        MailPreparation, MintStamps, PayInvoice, RedeemStamp, RegisterName, ReleaseName, RotateKey,
+        ScheduledTransfer, SetRoleLimits, SetWalletLimits, StampMailPreparation, SuspendWallet,
+        Transfer, TransferAsset, TransferStamp, TransferWithReference, UnsuspendWallet,
+    },
     wallet::Wallet,
-    CurrencyService,
+    CurrencySchema, CurrencyService, PREPARATION_TTL,
 };
 
 use exonum::encoding::serialize::FromHex;
+use serde::Serialize;
 
-use std::time::SystemTime;
+use std::{sync::Arc, thread, time::{Duration as StdDuration, Instant, SystemTime}};
 
 // Imports shared test constants.
 use constants::{ALICE_NAME, BOB_NAME, JOHN_NAME};
@@ -65,7 +90,7 @@ fn test_create_wallet() {
     println!("create = {}", serde_json::to_string_pretty(&tx).unwrap());
     assert_eq!(wallet.pub_key(), tx.pub_key());
     assert_eq!(wallet.name(), tx.name());
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 }
 #[test]
 fn test_issue() {
@@ -78,12 +103,12 @@ fn test_issue() {
     api.assert_tx_status(tx_bob.hash(), &json!({ "type": "success" }));
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 
     let tx = Issue :: new(
         tx_alice.pub_key(),
         tx_bob.pub_key(),
-        11,
+        Amount(11),
         0,
         &key_bob,
     );
@@ -92,9 +117,9 @@ fn test_issue() {
     testkit.create_block();
     api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 111);
+    assert_eq!(wallet.balance(), Amount(111));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);    
+    assert_eq!(wallet.balance(), Amount(100));    
 
 }
 
@@ -108,15 +133,15 @@ fn test_acceptance() {
     api.assert_tx_status(tx_bob.hash(), &json!({ "type": "success" }));
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     
     let meta = &String::new();
     let tx = MailPreparation :: new(
         meta,
         tx_alice.pub_key(),
-        11,
+        Amount(11),
         0,
         &key_alice,
     );
@@ -125,15 +150,15 @@ fn test_acceptance() {
     testkit.create_block();
     api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 89);
+    assert_eq!(wallet.balance(), Amount(89));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     
 
     let tx_accept = MailAcceptance :: new(
         tx_bob.pub_key(),
         tx_alice.pub_key(),
-        11,
+        Amount(11),
         false,
         1,
         &key_bob,
@@ -145,16 +170,16 @@ fn test_acceptance() {
     // After the transfer transaction is included into a block, we may check new wallet
     // balances.
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     
     let meta = &String::new();
 
     let tx2 = MailPreparation :: new(
         meta,
         tx_alice.pub_key(),
-        11,
+        Amount(11),
         2,
         &key_alice,
     );
@@ -163,15 +188,15 @@ fn test_acceptance() {
     testkit.create_block();
     api.assert_tx_status(tx2.hash(), &json!({ "type": "success" }));
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 89);
+    assert_eq!(wallet.balance(), Amount(89));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     
     //println!("{}", serde_json::to_string_pretty(&tx2).unwrap());
     let tx3 = MailAcceptance :: new(
         tx_bob.pub_key(),
         tx_alice.pub_key(),
-        11,
+        Amount(11),
         true,
         3,
         &key_bob,
@@ -183,12 +208,36 @@ fn test_acceptance() {
     // After the transfer transaction is included into a block, we may check new wallet
     // balances.
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 89);
+    assert_eq!(wallet.balance(), Amount(89));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
    // println!("{}", serde_json::to_string_pretty(&tx3).unwrap());
 }
 
+/// Check that a wallet with a pending mail preparation can't prepare another one.
+#[test]
+fn test_second_preparation() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    testkit.create_block();
+
+    let tx = MailPreparation::new("", tx_alice.pub_key(), Amount(10), 0, &key_alice);
+    api.preparation(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx_second = MailPreparation::new("", tx_alice.pub_key(), Amount(20), 1, &key_alice);
+    api.preparation(&tx_second);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx_second.hash(),
+        &json!({ "type": "error", "code": 11, "description": "Wallet has open mail items" }),
+    );
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(90));
+    assert_eq!(wallet.freezed_balance(), Amount(10));
+}
+
 #[test]
 fn test_preparation() {
     let (mut testkit, api, _) = create_testkit();
@@ -199,14 +248,14 @@ fn test_preparation() {
     api.assert_tx_status(tx_bob.hash(), &json!({ "type": "success" }));
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     let meta = &String::new();
     let tx = MailPreparation :: new(
         meta,
         tx_alice.pub_key(),
-        11,
+        Amount(11),
         0,
         &key_alice,
     );
@@ -218,9 +267,9 @@ fn test_preparation() {
     // After the transfer transaction is included into a block, we may check new wallet
     // balances.
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 89);
+    assert_eq!(wallet.balance(), Amount(89));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 }
 
 /// Check that the transfer transaction works as intended.
@@ -236,15 +285,15 @@ fn test_transfer() {
 
     // Check that the initial Alice's and Bob's balances persisted by the service.
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 
     // Transfer funds by invoking the corresponding API method.
     let tx = Transfer::new(
         tx_alice.pub_key(),
         tx_bob.pub_key(),
-        10, // transferred amount
+        Amount(10), // transferred amount
         0,  // seed
         &key_alice,
     );
@@ -256,9 +305,9 @@ fn test_transfer() {
     // After the transfer transaction is included into a block, we may check new wallet
     // balances.
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 90);
+    assert_eq!(wallet.balance(), Amount(90));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 110);
+    assert_eq!(wallet.balance(), Amount(110));
 }
 
 /// Check that a transfer from a non-existing wallet fails as expected.
@@ -274,12 +323,12 @@ fn test_transfer_from_nonexisting_wallet() {
 
     api.assert_no_wallet(*tx_alice.pub_key());
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 
     let tx = Transfer::new(
         tx_alice.pub_key(),
         tx_bob.pub_key(),
-        10, // transfer amount
+        Amount(10), // transfer amount
         0,  // seed
         &key_alice,
     );
@@ -292,7 +341,7 @@ fn test_transfer_from_nonexisting_wallet() {
 
     // Check that Bob's balance doesn't change.
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 }
 
 /// Check that a transfer to a non-existing wallet fails as expected.
@@ -307,13 +356,13 @@ fn test_transfer_to_nonexisting_wallet() {
     testkit.create_block_with_tx_hashes(&[tx_alice.hash()]);
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     api.assert_no_wallet(*tx_bob.pub_key());
 
     let tx = Transfer::new(
         tx_alice.pub_key(),
         tx_bob.pub_key(),
-        10, // transfer amount
+        Amount(10), // transfer amount
         0,  // seed
         &key_alice,
     );
@@ -326,7 +375,7 @@ fn test_transfer_to_nonexisting_wallet() {
 
     // Check that Alice's balance doesn't change.
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 }
 
 /// Check that an overcharge does not lead to changes in sender's and receiver's balances.
@@ -342,7 +391,7 @@ fn test_transfer_overcharge() {
     let tx = Transfer::new(
         tx_alice.pub_key(),
         tx_bob.pub_key(),
-        110, // transfer amount
+        Amount(110), // transfer amount
         0,   // seed
         &key_alice,
     );
@@ -354,9 +403,9 @@ fn test_transfer_overcharge() {
     );
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 }
 
 #[test]
@@ -384,13 +433,13 @@ fn test_cancellation_transfer() {
 
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     let tx_transfer = Transfer :: new(
     	tx_bob.pub_key(),
     	tx_alice.pub_key(),
-    	60,
+    	Amount(60),
     	4,
     	&key_bob,
     );
@@ -402,9 +451,9 @@ fn test_cancellation_transfer() {
     let tx_hash = &tx_transfer.hash();
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 160);
+    assert_eq!(wallet.balance(), Amount(160));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 40);
+    assert_eq!(wallet.balance(), Amount(40));
 
     let tx = Cancellation :: new(
         tx_john.pub_key(),
@@ -421,9 +470,9 @@ fn test_cancellation_transfer() {
     // balances.
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 }
 #[test]
 fn test_cancellation_issue() {
@@ -439,12 +488,12 @@ fn test_cancellation_issue() {
     //println!("keys_alice = {}, {}", serde_json::to_string_pretty(&tx_alice.pub_key()).unwrap(), serde_json::to_string_pretty(&key_alice).unwrap());
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 
     let tx_issue = Issue :: new(
     	tx_alice.pub_key(),
     	tx_bob.pub_key(),
-    	60,
+    	Amount(60),
     	4,
     	&key_bob,
     );
@@ -456,7 +505,7 @@ fn test_cancellation_issue() {
     let tx_hash = &tx_issue.hash();
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 160);
+    assert_eq!(wallet.balance(), Amount(160));
 
     let tx = Cancellation :: new(
         tx_john.pub_key(),
@@ -473,9 +522,9 @@ fn test_cancellation_issue() {
     // balances.
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
     let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 }
 #[test]
 fn test_cancellation_mailpreparation() {
@@ -489,13 +538,13 @@ fn test_cancellation_mailpreparation() {
 
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 
     let meta = &String::new();
     let tx_preparation = MailPreparation :: new(
         meta,
         tx_alice.pub_key(),
-        11,
+        Amount(11),
         0,
         &key_alice,
     );
@@ -506,7 +555,7 @@ fn test_cancellation_mailpreparation() {
     let tx_hash = &tx_preparation.hash();
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 89);
+    assert_eq!(wallet.balance(), Amount(89));
 
     let tx = Cancellation :: new(
         tx_bob.pub_key(),
@@ -521,7 +570,7 @@ fn test_cancellation_mailpreparation() {
 
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 }
 #[test]
 fn test_cancellation_mailacceptance() {
@@ -535,14 +584,14 @@ fn test_cancellation_mailacceptance() {
 
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 
 
     let meta = &String::new();
     let tx_preparation = MailPreparation :: new(
         meta,
         tx_alice.pub_key(),
-        11,
+        Amount(11),
         0,
         &key_alice,
     );
@@ -552,13 +601,13 @@ fn test_cancellation_mailacceptance() {
     api.assert_tx_status(tx_preparation.hash(), &json!({ "type": "success" }));
     
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 89);
+    assert_eq!(wallet.balance(), Amount(89));
 
 
     let tx_accept = MailAcceptance :: new(
         tx_bob.pub_key(),
         tx_alice.pub_key(),
-        11,
+        Amount(11),
         true,
         1,
         &key_bob,
@@ -571,7 +620,7 @@ fn test_cancellation_mailacceptance() {
     let tx_hash = &tx_accept.hash();
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 89);
+    assert_eq!(wallet.balance(), Amount(89));
 
     let tx = Cancellation :: new(
         tx_bob.pub_key(),
@@ -586,140 +635,2617 @@ fn test_cancellation_mailacceptance() {
 
 
     let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
-    assert_eq!(wallet.balance(), 100);
+    assert_eq!(wallet.balance(), Amount(100));
 }
-/// Wrapper for the cryptocurrency service API allowing to easily use it
-/// (compared to `TestKitApi` calls).
-struct CryptocurrencyApi {
-    pub inner: TestKitApi,
+/// Check that a wallet can be moved to a new key together with its balance and roles.
+#[test]
+fn test_rotate_key() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 2);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let tx = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(10), 0, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let (new_pub_key, new_key) = crypto::gen_keypair();
+    let payload = rotation_payload(tx_alice.pub_key(), &new_pub_key, 0);
+    let new_key_signature = crypto::sign(payload.as_ref(), &new_key);
+    let tx = RotateKey::new(
+        tx_alice.pub_key(),
+        &new_pub_key,
+        &new_key_signature,
+        0,
+        &key_alice,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    api.assert_no_wallet(*tx_alice.pub_key());
+    let wallet = api.get_wallet(new_pub_key).unwrap();
+    assert_eq!(wallet.name(), ALICE_NAME);
+    assert_eq!(wallet.balance(), Amount(110));
+    assert_eq!(wallet.history_len(), 3);
+
+    // Issuer role is moved to the new key, and transfers to the old key are forwarded.
+    let tx_issue = Issue::new(tx_bob.pub_key(), &new_pub_key, Amount(5), 0, &new_key);
+    api.send(&tx_issue);
+    let tx_transfer = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(20), 1, &key_bob);
+    api.send(&tx_transfer);
+    testkit.create_block();
+    api.assert_tx_status(tx_issue.hash(), &json!({ "type": "success" }));
+    api.assert_tx_status(tx_transfer.hash(), &json!({ "type": "success" }));
+
+    let wallet = api.get_wallet(new_pub_key).unwrap();
+    assert_eq!(wallet.balance(), Amount(135));
+    let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(75));
 }
 
-impl CryptocurrencyApi {
-    /// Generates a wallet creation transaction with a random key pair, sends it over HTTP,
-    /// and checks the synchronous result (i.e., the hash of the transaction returned
-    /// within the response).
-    /// Note that the transaction is not immediately added to the blockchain, but rather is put
-    /// to the pool of unconfirmed transactions.
-    fn create_wallet(&self, name: &str, user: u64) -> (CreateWallet, SecretKey) {
-        let (pubkey, key) = crypto::gen_keypair();
-        // Create a pre-signed transaction
-        let tx = CreateWallet::new(&pubkey, name, user, &key);
-        let tx_info: serde_json::Value = self.inner
-            .public(ApiKind::Service("cryptocurrency"))
-            .query(&tx)
-            .post("v1/wallets/transaction")
-            .unwrap();
-        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
-        (tx, key)
-    }
+/// Check that a wallet cannot be moved to a key that is already in use.
+#[test]
+fn test_rotate_key_to_existing_wallet() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
 
-    fn get_wallet(&self, pub_key: PublicKey) -> Option<Wallet> {
-        let wallet_info = self.inner
-            .public(ApiKind::Service("cryptocurrency"))
-            .query(&WalletQuery { pub_key })
-            .get::<WalletInfo>("v1/wallets/info")
-            .unwrap();
+    let payload = rotation_payload(tx_alice.pub_key(), tx_bob.pub_key(), 0);
+    let new_key_signature = crypto::sign(payload.as_ref(), &key_bob);
+    let tx = RotateKey::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        &new_key_signature,
+        0,
+        &key_alice,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 0, "description": "Wallet already exists" }),
+    );
 
-        let to_wallet = wallet_info.wallet_proof.to_wallet.check().unwrap();
-        to_wallet
-            .all_entries()
-            .iter()
-            .find(|(ref k, _)| **k == pub_key)
-            .and_then(|tuple| tuple.1)
-            .cloned()
-    }
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(100));
+}
 
-    fn preparation(&self, tx: &MailPreparation) {
-        let tx_info: serde_json::Value = self.inner
-            .public(ApiKind::Service("cryptocurrency"))
-            .query(&tx)
-            .post("v1/wallets/transaction")
-            .unwrap();
-        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
-    }
+/// Check that the new key signature covers the whole rotation, so that it cannot be
+/// reused for another rotation.
+#[test]
+fn test_rotate_key_signature() {
+    let (_, key_alice) = crypto::gen_keypair();
+    let (pub_key, _) = crypto::gen_keypair();
+    let (new_pub_key, new_key) = crypto::gen_keypair();
+
+    let legacy_signature = crypto::sign(pub_key.as_ref(), &new_key);
+    let tx = RotateKey::new(&pub_key, &new_pub_key, &legacy_signature, 0, &key_alice);
+    assert!(!tx.verify());
+
+    let payload = rotation_payload(&pub_key, &new_pub_key, 0);
+    let new_key_signature = crypto::sign(payload.as_ref(), &new_key);
+    let tx = RotateKey::new(&pub_key, &new_pub_key, &new_key_signature, 1, &key_alice);
+    assert!(!tx.verify());
+}
 
-    fn acceptance(&self, tx: &MailAcceptance) {
-        let tx_info: serde_json::Value = self.inner
-            .public(ApiKind::Service("cryptocurrency"))
-            .query(&tx)
-            .post("v1/wallets/transaction")
-            .unwrap();
-        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));        
-    }
-    
-    /// Sends a transfer transaction over HTTP and checks the synchronous result.
-    fn transfer(&self, tx: &Transfer) {
-        let tx_info: serde_json::Value = self.inner
-            .public(ApiKind::Service("cryptocurrency"))
-            .query(&tx)
-            .post("v1/wallets/transaction")
-            .unwrap();
-        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
-    }
+/// Check that mail prepared before a key rotation can be accepted by the old key.
+#[test]
+fn test_accept_mail_after_key_rotation() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 1);
+    testkit.create_block();
 
-    fn issue(&self, tx: &Issue) {
-        let tx_info: serde_json::Value = self.inner
-            .public(ApiKind::Service("cryptocurrency"))
-            .query(&tx)
-            .post("v1/wallets/transaction")
-            .unwrap();
-        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
-    }
+    let tx_preparation = MailPreparation::new("", tx_alice.pub_key(), Amount(11), 0, &key_alice);
+    api.preparation(&tx_preparation);
+    testkit.create_block();
 
-    fn cancellation(&self, tx: &Cancellation) {
-    	let tx_info: serde_json::Value = self.inner
-            .public(ApiKind::Service("cryptocurrency"))
-            .query(&tx)
-            .post("v1/wallets/transaction")
-            .unwrap();
+    let (new_pub_key, new_key) = crypto::gen_keypair();
+    let payload = rotation_payload(tx_alice.pub_key(), &new_pub_key, 0);
+    let new_key_signature = crypto::sign(payload.as_ref(), &new_key);
+    let tx = RotateKey::new(tx_alice.pub_key(), &new_pub_key, &new_key_signature, 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
 
-        assert_eq!(tx_info, json!({ "tx_hash": tx.hash()}));
-    }
+    let tx_accept =
+        MailAcceptance::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(11), false, 1, &key_bob);
+    api.acceptance(&tx_accept);
+    testkit.create_block();
+    api.assert_tx_status(tx_accept.hash(), &json!({ "type": "success" }));
 
-    /// Asserts that a wallet with the specified public key is not known to the blockchain.
-    fn assert_no_wallet(&self, pub_key: PublicKey) {
-        let wallet_info: WalletInfo = self.inner
-            .public(ApiKind::Service("cryptocurrency"))
-            .query(&WalletQuery { pub_key })
-            .get("v1/wallets/info")
-            .unwrap();
+    let wallet = api.get_wallet(new_pub_key).unwrap();
+    assert_eq!(wallet.balance(), Amount(100));
+    assert_eq!(wallet.freezed_balance(), Amount(0));
+}
 
-        let to_wallet = wallet_info.wallet_proof.to_wallet.check().unwrap();
-        assert!(
-            to_wallet
-                .missing_keys()
-                .iter()
-                .find(|v| ***v == pub_key)
-                .is_some()
-        )
-    }
+/// Check that a suspended wallet cannot send funds until the suspension is lifted.
+#[test]
+fn test_suspend_wallet() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 0);
+    testkit.create_block();
+    assign_role(&mut testkit, &api, tx_john.pub_key(), 3);
 
-    /// Asserts that the transaction with the given hash has a specified status.
-    fn assert_tx_status(&self, tx_hash: Hash, expected_status: &serde_json::Value) {
-        let info: serde_json::Value = self.inner
-            .public(ApiKind::Explorer)
-            .query(&TransactionQuery::new(tx_hash))
-            .get("v1/transactions")
-            .unwrap();
-        if let serde_json::Value::Object(mut info) = info {
-            let tx_status = info.remove("status").unwrap();
-            assert_eq!(tx_status, *expected_status);
-        } else {
-            panic!("Invalid transaction info format, object expected");
-        }
-    }
+    let tx = SuspendWallet::new(tx_john.pub_key(), tx_alice.pub_key(), 0, &key_john);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    assert!(api.get_wallet(*tx_alice.pub_key()).unwrap().suspended());
+
+    let tx_transfer =
+        Transfer::new(tx_alice.pub_key(), tx_bob.pub_key(), Amount(10), 0, &key_alice);
+    api.send(&tx_transfer);
+    let tx_preparation = MailPreparation::new("", tx_alice.pub_key(), Amount(10), 0, &key_alice);
+    api.send(&tx_preparation);
+    testkit.create_block();
+    let suspended = json!({ "type": "error", "code": 8, "description": "Wallet is suspended" });
+    api.assert_tx_status(tx_transfer.hash(), &suspended);
+    api.assert_tx_status(tx_preparation.hash(), &suspended);
+
+    let tx = UnsuspendWallet::new(tx_john.pub_key(), tx_alice.pub_key(), 1, &key_john);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx_transfer =
+        Transfer::new(tx_alice.pub_key(), tx_bob.pub_key(), Amount(10), 1, &key_alice);
+    api.send(&tx_transfer);
+    testkit.create_block();
+    api.assert_tx_status(tx_transfer.hash(), &json!({ "type": "success" }));
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(90));
+    assert!(!wallet.suspended());
 }
 
-/// Creates a testkit together with the API wrapper defined above.
-fn create_testkit() -> (TestKit, CryptocurrencyApi, MockTimeProvider) {
-	let mock_provider = MockTimeProvider::new(SystemTime::now().into());
-    let mut testkit = TestKitBuilder::validator()
-        .with_service(CurrencyService)
-        .with_service(TimeService::with_provider(mock_provider.clone()))
-        .create();
-    let api = CryptocurrencyApi {
-        inner: testkit.api(),
-    };
-    testkit.create_blocks_until(Height(2)); 
-    (testkit, api, mock_provider)
+/// Check that only compliance officers can suspend wallets.
+#[test]
+fn test_suspend_wallet_not_compliance_officer() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 1);
+    testkit.create_block();
+
+    let tx = SuspendWallet::new(tx_bob.pub_key(), tx_alice.pub_key(), 0, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({
+            "type": "error",
+            "code": 9,
+            "description": "Pubkey doesn`t belong to compliance officer"
+        }),
+    );
+    assert!(!api.get_wallet(*tx_alice.pub_key()).unwrap().suspended());
+}
+
+/// Check that the compliance officer role is assigned by validators only, and that
+/// a suspended officer cannot lift its own suspension.
+#[test]
+fn test_assign_compliance_officer() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 3);
+    testkit.create_block();
+    api.assert_tx_status(tx_john.hash(), &json!({ "type": "success" }));
+
+    let tx = SuspendWallet::new(tx_john.pub_key(), tx_alice.pub_key(), 0, &key_john);
+    api.send(&tx);
+    let tx_assign = AssignRole::new(tx_john.pub_key(), tx_john.pub_key(), 3, 0, &key_john);
+    api.send(&tx_assign);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({
+            "type": "error",
+            "code": 9,
+            "description": "Pubkey doesn`t belong to compliance officer"
+        }),
+    );
+    api.assert_tx_status(
+        tx_assign.hash(),
+        &json!({
+            "type": "error",
+            "code": 25,
+            "description": "Pubkey doesn`t belong to validator"
+        }),
+    );
+
+    assign_role(&mut testkit, &api, tx_john.pub_key(), 3);
+    let tx = SuspendWallet::new(tx_john.pub_key(), tx_john.pub_key(), 1, &key_john);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx = UnsuspendWallet::new(tx_john.pub_key(), tx_john.pub_key(), 2, &key_john);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 8, "description": "Wallet is suspended" }),
+    );
+    assert!(api.get_wallet(*tx_john.pub_key()).unwrap().suspended());
+}
+
+/// Check that closing a wallet sweeps its balance and keeps its final state provable.
+#[test]
+fn test_close_wallet() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let tx = CloseWallet::new(tx_alice.pub_key(), tx_bob.pub_key(), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    api.assert_no_wallet(*tx_alice.pub_key());
+    let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(200));
+
+    let wallet_info = api.get_wallet_info(*tx_alice.pub_key());
+    let closed_wallet = wallet_info
+        .closed_wallet_proof
+        .unwrap()
+        .to_wallet
+        .check()
+        .unwrap()
+        .entries()
+        .into_iter()
+        .map(|(_, wallet)| wallet.clone())
+        .next()
+        .unwrap();
+    assert_eq!(closed_wallet.balance(), Amount(0));
+    assert_eq!(closed_wallet.history_len(), 2);
+    assert_eq!(wallet_info.wallet_history.unwrap().transactions.len(), 2);
+
+    // Closed key cannot be used for a new wallet.
+    let tx = CreateWallet::new(tx_alice.pub_key(), ALICE_NAME, 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 0, "description": "Wallet already exists" }),
+    );
+}
+
+/// Check that closing a wallet cancels its scheduled transfers, returning the reserved
+/// funds to the beneficiary, and releases its names.
+#[test]
+fn test_close_wallet_with_schedules_and_names() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let execute_at = Utc::now() + Duration::hours(1);
+    let tx_scheduled = ScheduledTransfer::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(10),
+        execute_at,
+        0,
+        &key_alice,
+    );
+    api.send(&tx_scheduled);
+    let tx_name = RegisterName::new(tx_alice.pub_key(), "alice", 0, &key_alice);
+    api.send(&tx_name);
+    testkit.create_block();
+    api.assert_tx_status(tx_scheduled.hash(), &json!({ "type": "success" }));
+    api.assert_tx_status(tx_name.hash(), &json!({ "type": "success" }));
+
+    let tx = CloseWallet::new(tx_alice.pub_key(), tx_bob.pub_key(), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(200));
+
+    let (validator_key, validator_secret) = validator_keypair(&testkit);
+    let tx_execute =
+        ExecuteScheduled::new(&validator_key, &tx_scheduled.hash(), 0, &validator_secret);
+    api.send(&tx_execute);
+    let tx_name = RegisterName::new(tx_bob.pub_key(), "alice", 0, &key_bob);
+    api.send(&tx_name);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx_execute.hash(),
+        &json!({ "type": "error", "code": 15, "description": "Scheduled transfer doesn't exist" }),
+    );
+    api.assert_tx_status(tx_name.hash(), &json!({ "type": "success" }));
+}
+
+/// Check that the balance swept by closing a wallet is subject to its spending limits.
+#[test]
+fn test_close_wallet_limits() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 0);
+    testkit.create_block();
+    assign_role(&mut testkit, &api, tx_john.pub_key(), 3);
+
+    let tx = SetWalletLimits::new(tx_john.pub_key(), tx_alice.pub_key(), 30, 0, 0, 0, &key_john);
+    api.send(&tx);
+    testkit.create_block();
+
+    let tx = CloseWallet::new(tx_alice.pub_key(), tx_bob.pub_key(), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 12, "description": "Transfer limit exceeded" }),
+    );
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(100));
+}
+
+/// Check that a wallet with frozen funds cannot be closed.
+#[test]
+fn test_close_wallet_with_open_mail_items() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let tx = MailPreparation::new("", tx_alice.pub_key(), Amount(10), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+
+    let tx = CloseWallet::new(tx_alice.pub_key(), tx_bob.pub_key(), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 11, "description": "Wallet has open mail items" }),
+    );
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(90));
+}
+
+/// Check that transfers are limited by the wallet limits.
+#[test]
+fn test_wallet_limits() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 0);
+    testkit.create_block();
+    assign_role(&mut testkit, &api, tx_john.pub_key(), 3);
+
+    let tx = SetWalletLimits::new(tx_john.pub_key(), tx_alice.pub_key(), 30, 50, 0, 0, &key_john);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx = Transfer::new(tx_alice.pub_key(), tx_bob.pub_key(), Amount(40), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 12, "description": "Transfer limit exceeded" }),
+    );
+
+    let tx = Transfer::new(tx_alice.pub_key(), tx_bob.pub_key(), Amount(30), 1, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx = Transfer::new(tx_alice.pub_key(), tx_bob.pub_key(), Amount(30), 2, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 13, "description": "Daily outbound limit exceeded" }),
+    );
+
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(70));
+}
+
+/// Check that the daily outbound volume is kept when the wallet rotates its key.
+#[test]
+fn test_wallet_limits_after_key_rotation() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 0);
+    testkit.create_block();
+    assign_role(&mut testkit, &api, tx_john.pub_key(), 3);
+
+    let tx = SetWalletLimits::new(tx_john.pub_key(), tx_alice.pub_key(), 0, 50, 0, 0, &key_john);
+    api.send(&tx);
+    let tx = Transfer::new(tx_alice.pub_key(), tx_bob.pub_key(), Amount(40), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let (new_pub_key, new_key) = crypto::gen_keypair();
+    let payload = rotation_payload(tx_alice.pub_key(), &new_pub_key, 0);
+    let new_key_signature = crypto::sign(payload.as_ref(), &new_key);
+    let tx = RotateKey::new(tx_alice.pub_key(), &new_pub_key, &new_key_signature, 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx = Transfer::new(&new_pub_key, tx_bob.pub_key(), Amount(20), 1, &new_key);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 13, "description": "Daily outbound limit exceeded" }),
+    );
+    let wallet = api.get_wallet(new_pub_key).unwrap();
+    assert_eq!(wallet.balance(), Amount(60));
+}
+
+/// Check that issuance is limited by the limits of the issuer role.
+#[test]
+fn test_role_issue_limits() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 2);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 0);
+    testkit.create_block();
+    assign_role(&mut testkit, &api, tx_john.pub_key(), 3);
+
+    let tx = SetRoleLimits::new(tx_john.pub_key(), 2, 0, 0, 20, 0, &key_john);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx = Issue::new(tx_alice.pub_key(), tx_bob.pub_key(), Amount(15), 0, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx = Issue::new(tx_alice.pub_key(), tx_bob.pub_key(), Amount(10), 1, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 14, "description": "Daily issue limit exceeded" }),
+    );
+
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(115));
+}
+
+/// Check that a suspended compliance officer cannot set limits and that limits set by
+/// an active officer are timestamped.
+#[test]
+fn test_limits_suspended_officer() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 0);
+    let (tx_carol, key_carol) = api.create_wallet("Carol", 0);
+    testkit.create_block();
+    assign_role(&mut testkit, &api, tx_john.pub_key(), 3);
+    assign_role(&mut testkit, &api, tx_carol.pub_key(), 3);
+
+    let tx = SuspendWallet::new(tx_carol.pub_key(), tx_john.pub_key(), 0, &key_carol);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx_wallet =
+        SetWalletLimits::new(tx_john.pub_key(), tx_alice.pub_key(), 30, 0, 0, 0, &key_john);
+    api.send(&tx_wallet);
+    let tx_role = SetRoleLimits::new(tx_john.pub_key(), 0, 30, 0, 0, 0, &key_john);
+    api.send(&tx_role);
+    testkit.create_block();
+    let suspended = json!({ "type": "error", "code": 8, "description": "Wallet is suspended" });
+    api.assert_tx_status(tx_wallet.hash(), &suspended);
+    api.assert_tx_status(tx_role.hash(), &suspended);
+
+    let tx = SetWalletLimits::new(tx_carol.pub_key(), tx_alice.pub_key(), 30, 0, 0, 0, &key_carol);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let query = TimeRangeQuery {
+        from: Utc::now() - Duration::hours(1),
+        to: Utc::now() + Duration::hours(1),
+        office: None,
+    };
+    let entries = api.get_entries_by_time(&query);
+    assert!(entries.iter().any(|entry| *entry.tx_hash() == tx.hash()));
+    assert!(entries.iter().all(|entry| *entry.tx_hash() != tx_wallet.hash()));
+}
+
+/// Check that a scheduled transfer reserves funds and is executed once its time has come.
+#[test]
+fn test_scheduled_transfer() {
+    let (mut testkit, api, mock_provider) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let execute_at = Utc::now() + Duration::hours(1);
+    let tx = ScheduledTransfer::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(10),
+        execute_at,
+        0,
+        &key_alice,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    // Funds are reserved, but not transferred yet.
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(90));
+    let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(100));
+
+    // Only validators can execute scheduled transfers.
+    let tx_execute = ExecuteScheduled::new(tx_bob.pub_key(), &tx.hash(), 0, &key_bob);
+    api.send(&tx_execute);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx_execute.hash(),
+        &json!({
+            "type": "error",
+            "code": 25,
+            "description": "Pubkey doesn`t belong to validator"
+        }),
+    );
+
+    let (validator_key, validator_secret) = validator_keypair(&testkit);
+    let tx_execute = ExecuteScheduled::new(&validator_key, &tx.hash(), 0, &validator_secret);
+    api.send(&tx_execute);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx_execute.hash(),
+        &json!({ "type": "error", "code": 16, "description": "Scheduled time is not reached" }),
+    );
+
+    // Validator executes the transfer after the time oracle passes `execute_at`.
+    mock_provider.set_time(execute_at + Duration::minutes(1));
+    for _ in 0..3 {
+        testkit.create_block();
+    }
+
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(90));
+    let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(110));
+}
+
+/// Check that a scheduled transfer of a wallet suspended before its execution is
+/// refunded to the sender.
+#[test]
+fn test_scheduled_transfer_suspended_sender() {
+    let (mut testkit, api, mock_provider) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 0);
+    testkit.create_block();
+    assign_role(&mut testkit, &api, tx_john.pub_key(), 3);
+
+    let execute_at = Utc::now() + Duration::hours(1);
+    let tx = ScheduledTransfer::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(10),
+        execute_at,
+        0,
+        &key_alice,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let tx_suspend = SuspendWallet::new(tx_john.pub_key(), tx_alice.pub_key(), 0, &key_john);
+    api.send(&tx_suspend);
+    testkit.create_block();
+    api.assert_tx_status(tx_suspend.hash(), &json!({ "type": "success" }));
+
+    mock_provider.set_time(execute_at + Duration::minutes(1));
+    for _ in 0..3 {
+        testkit.create_block();
+    }
+
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().balance(), Amount(100));
+    assert_eq!(api.get_wallet(*tx_bob.pub_key()).unwrap().balance(), Amount(100));
+    let log = api.get_events(0, None);
+    assert!(log.events.iter().any(|record| record.event() == PostEvent::TransferRefunded {
+        transfer: tx.hash(),
+        pub_key: *tx_alice.pub_key(),
+        amount: Amount(10),
+    }));
+}
+
+/// Check that a scheduled transfer to a closed wallet is refunded to the sender.
+#[test]
+fn test_scheduled_transfer_refund() {
+    let (mut testkit, api, mock_provider) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    let (tx_john, _) = api.create_wallet(JOHN_NAME, 0);
+    testkit.create_block();
+
+    let execute_at = Utc::now() + Duration::hours(1);
+    let tx = ScheduledTransfer::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(10),
+        execute_at,
+        0,
+        &key_alice,
+    );
+    api.send(&tx);
+    let tx_close = CloseWallet::new(tx_bob.pub_key(), tx_john.pub_key(), 0, &key_bob);
+    api.send(&tx_close);
+    testkit.create_block();
+    api.assert_tx_status(tx_close.hash(), &json!({ "type": "success" }));
+
+    mock_provider.set_time(execute_at + Duration::minutes(1));
+    for _ in 0..3 {
+        testkit.create_block();
+    }
+
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(100));
+    let log = api.get_events(0, None);
+    assert!(log.events.iter().any(|record| record.event() == PostEvent::TransferRefunded {
+        transfer: tx.hash(),
+        pub_key: *tx_alice.pub_key(),
+        amount: Amount(10),
+    }));
+}
+
+/// Check that a standing order pays at every period boundary until its end.
+#[test]
+fn test_standing_order() {
+    let (mut testkit, api, mock_provider) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let start = Utc::now() + Duration::hours(1);
+    let tx = CreateStandingOrder::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(15),
+        3600,
+        start,
+        start + Duration::minutes(90),
+        0,
+        &key_alice,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    for &offset in &[1, 61, 121] {
+        mock_provider.set_time(start + Duration::minutes(offset));
+        for _ in 0..3 {
+            testkit.create_block();
+        }
+    }
+
+    // The order has ended after two payments.
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(70));
+    let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(130));
+
+    let history = api.get_wallet_info(*tx_bob.pub_key()).wallet_history.unwrap();
+    assert_eq!(history.transactions.len(), 1);
+    assert_eq!(history.standing_order_payments.len(), 2);
+    assert_eq!(history.standing_order_payments[1].number(), 2);
+}
+
+/// Check that only the payer can cancel a standing order.
+#[test]
+fn test_cancel_standing_order() {
+    let (mut testkit, api, mock_provider) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let start = Utc::now() + Duration::hours(1);
+    let tx_order = CreateStandingOrder::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(15),
+        3600,
+        start,
+        start + Duration::days(30),
+        0,
+        &key_alice,
+    );
+    api.send(&tx_order);
+    testkit.create_block();
+
+    let tx = CancelStandingOrder::new(tx_bob.pub_key(), &tx_order.hash(), 0, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({
+            "type": "error",
+            "code": 18,
+            "description": "Pubkey doesn`t belong to standing order payer"
+        }),
+    );
+
+    let tx = CancelStandingOrder::new(tx_alice.pub_key(), &tx_order.hash(), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    mock_provider.set_time(start + Duration::minutes(1));
+    for _ in 0..3 {
+        testkit.create_block();
+    }
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(100));
+}
+
+/// Check that a standing order must pay a positive amount and cannot start in the past.
+#[test]
+fn test_create_standing_order_checks() {
+    let (mut testkit, api, mock_provider) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let start = Utc::now() + Duration::hours(1);
+    let tx = CreateStandingOrder::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(0),
+        3600,
+        start,
+        start + Duration::days(30),
+        0,
+        &key_alice,
+    );
+    assert!(!tx.verify());
+
+    mock_provider.set_time(start + Duration::minutes(1));
+    for _ in 0..3 {
+        testkit.create_block();
+    }
+    let tx = CreateStandingOrder::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(15),
+        3600,
+        start,
+        start + Duration::days(30),
+        0,
+        &key_alice,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 43, "description": "Time is in the past" }),
+    );
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(100));
+}
+
+/// Check that an invoice can be paid once by its payer and is listed for both parties.
+#[test]
+fn test_invoice() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 0);
+    testkit.create_block();
+
+    let tx_invoice = CreateInvoice::new(
+        tx_bob.pub_key(),
+        tx_alice.pub_key(),
+        Amount(25),
+        "POSTAGE-2018-10",
+        Utc::now() + Duration::days(30),
+        0,
+        &key_bob,
+    );
+    api.send(&tx_invoice);
+    testkit.create_block();
+    api.assert_tx_status(tx_invoice.hash(), &json!({ "type": "success" }));
+
+    let tx = PayInvoice::new(tx_john.pub_key(), &tx_invoice.hash(), 0, &key_john);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({
+            "type": "error",
+            "code": 22,
+            "description": "Pubkey doesn`t belong to invoice payer"
+        }),
+    );
+
+    let tx = PayInvoice::new(tx_alice.pub_key(), &tx_invoice.hash(), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx = PayInvoice::new(tx_alice.pub_key(), &tx_invoice.hash(), 1, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 21, "description": "Invoice is already paid" }),
+    );
+
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(75));
+    let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(125));
+
+    let invoices: Vec<Invoice> = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&WalletQuery { pub_key: *tx_alice.pub_key() })
+        .get("v1/invoices/by-payer")
+        .unwrap();
+    assert_eq!(invoices.len(), 1);
+    assert_eq!(invoices[0].reference(), "POSTAGE-2018-10");
+    assert!(invoices[0].paid());
+
+    let invoices: Vec<Invoice> = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&WalletQuery { pub_key: *tx_bob.pub_key() })
+        .get("v1/invoices/by-payee")
+        .unwrap();
+    assert_eq!(invoices.len(), 1);
+    assert_eq!(invoices[0].tx_hash(), &tx_invoice.hash());
+}
+
+/// Check that invoices stay listed for the payer after it rotates its key.
+#[test]
+fn test_invoices_after_key_rotation() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let (alice, bob) = (tx_alice.pub_key(), tx_bob.pub_key());
+    let expires_at = Utc::now() + Duration::days(30);
+    let tx = CreateInvoice::new(bob, alice, Amount(25), "A", expires_at, 0, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+
+    let (new_pub_key, new_key) = crypto::gen_keypair();
+    let payload = rotation_payload(alice, &new_pub_key, 0);
+    let new_key_signature = crypto::sign(payload.as_ref(), &new_key);
+    let tx = RotateKey::new(alice, &new_pub_key, &new_key_signature, 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    // Invoices addressed to the old key after the rotation are listed as well.
+    let tx = CreateInvoice::new(bob, alice, Amount(5), "B", expires_at, 1, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    for pub_key in &[new_pub_key, *alice] {
+        let invoices: Vec<Invoice> = api.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&WalletQuery { pub_key: *pub_key })
+            .get("v1/invoices/by-payer")
+            .unwrap();
+        let references = invoices.iter().map(Invoice::reference).collect::<Vec<_>>();
+        assert_eq!(references, vec!["A", "B"]);
+    }
+}
+
+/// Check that transfers with a reference can be found by it.
+#[test]
+fn test_transfer_with_reference() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let tx_first = TransferWithReference::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(10),
+        0,
+        "ERP-0042",
+        &key_alice,
+    );
+    let tx_second = TransferWithReference::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(5),
+        1,
+        "ERP-0042",
+        &key_alice,
+    );
+    let tx_other = TransferWithReference::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(1),
+        2,
+        "ERP-0043",
+        &key_alice,
+    );
+    let tx_empty = TransferWithReference::new(
+        tx_alice.pub_key(),
+        tx_bob.pub_key(),
+        Amount(1),
+        3,
+        "",
+        &key_alice,
+    );
+    api.send(&tx_first);
+    api.send(&tx_second);
+    api.send(&tx_other);
+    api.send(&tx_empty);
+    testkit.create_block();
+    api.assert_tx_status(tx_first.hash(), &json!({ "type": "success" }));
+    api.assert_tx_status(tx_empty.hash(), &json!({ "type": "success" }));
+
+    let wallet = api.get_wallet(*tx_bob.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(117));
+
+    let transfers: Vec<TransferWithReference> = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&ReferenceQuery { reference: "ERP-0042".to_owned() })
+        .get("v1/transfers/by-reference")
+        .unwrap();
+    let mut hashes = transfers.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+    let mut expected = vec![tx_first.hash(), tx_second.hash()];
+    hashes.sort();
+    expected.sort();
+    assert_eq!(hashes, expected);
+
+    // Transfers without a reference are not listed.
+    let transfers: Vec<TransferWithReference> = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&ReferenceQuery { reference: String::new() })
+        .get("v1/transfers/by-reference")
+        .unwrap();
+    assert!(transfers.is_empty());
+}
+
+/// Check that registered names resolve to wallets and are unique.
+#[test]
+fn test_name_directory() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let tx = RegisterName::new(tx_alice.pub_key(), "Ivanov-12345", 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let lookup: NameLookup = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&NameQuery { name: " ivanov-12345 ".to_owned() })
+        .get("v1/wallets/lookup")
+        .unwrap();
+    assert_eq!(lookup.pub_key, *tx_alice.pub_key());
+    assert_eq!(lookup.wallet.name(), ALICE_NAME);
+
+    let tx = RegisterName::new(tx_bob.pub_key(), "IVANOV-12345", 0, &key_bob);
+    api.send(&tx);
+    let tx_release = ReleaseName::new(tx_bob.pub_key(), "ivanov-12345", 0, &key_bob);
+    api.send(&tx_release);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 23, "description": "Name is already taken" }),
+    );
+    api.assert_tx_status(
+        tx_release.hash(),
+        &json!({ "type": "error", "code": 24, "description": "Name doesn`t belong to the wallet" }),
+    );
+
+    let tx_release = ReleaseName::new(tx_alice.pub_key(), "ivanov-12345", 0, &key_alice);
+    api.send(&tx_release);
+    testkit.create_block();
+    api.assert_tx_status(tx_release.hash(), &json!({ "type": "success" }));
+
+    let tx = RegisterName::new(tx_bob.pub_key(), "ivanov-12345", 1, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+}
+
+/// Check that lookalike names are normalized or rejected.
+#[test]
+fn test_name_normalization() {
+    // Fullwidth letters are brought to the NFKC form.
+    assert_eq!(normalize_name("ＩＶＡＮＯＶ").unwrap(), "ivanov");
+    assert_eq!(normalize_name("Иванов-12345").unwrap(), "иванов-12345");
+    // Latin name with a Cyrillic `а`.
+    assert_eq!(normalize_name("\u{430}lice"), None);
+
+    let (pub_key, sec_key) = crypto::gen_keypair();
+    let tx = RegisterName::new(&pub_key, "\u{430}lice", 0, &sec_key);
+    assert!(!tx.verify());
+}
+
+/// Check that a wallet can hold a limited number of names.
+#[test]
+fn test_wallet_names_limit() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    testkit.create_block();
+
+    for i in 0..MAX_WALLET_NAMES {
+        let name = format!("alice-{}", i);
+        api.send(&RegisterName::new(tx_alice.pub_key(), &name, 0, &key_alice));
+    }
+    testkit.create_block();
+    let tx = RegisterName::new(tx_alice.pub_key(), "alice", 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 44, "description": "Wallet has too many names" }),
+    );
+}
+
+/// Check that wallets and roles can be listed page by page.
+#[test]
+fn test_list_wallets() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 1);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 2);
+    let (tx_john, _) = api.create_wallet(JOHN_NAME, 1);
+    testkit.create_block();
+
+    let mut keys = Vec::new();
+    let mut query = PageQuery { from: None, count: Some(2) };
+    loop {
+        let page: Page<Wallet> = api.inner
+            .private(ApiKind::Service("cryptocurrency"))
+            .query(&query)
+            .get("v1/wallets")
+            .unwrap();
+        assert!(page.items.len() <= 2);
+        keys.extend(page.items.iter().map(|wallet| *wallet.pub_key()));
+        match page.next {
+            Some(next) => query.from = Some(next),
+            None => break,
+        }
+    }
+    let mut expected = vec![*tx_alice.pub_key(), *tx_bob.pub_key(), *tx_john.pub_key()];
+    expected.sort();
+    keys.sort();
+    assert_eq!(keys, expected);
+
+    let inspectors: Page<PublicKey> = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&PageQuery::default())
+        .get("v1/inspectors")
+        .unwrap();
+    assert_eq!(inspectors.items.len(), 2);
+    assert!(inspectors.next.is_none());
+
+    let issuers: Page<PublicKey> = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&PageQuery::default())
+        .get("v1/issuers")
+        .unwrap();
+    assert_eq!(issuers.items, vec![*tx_bob.pub_key()]);
+
+    // Empty pages cannot be requested.
+    let response = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&PageQuery { from: None, count: Some(0) })
+        .get::<Page<PublicKey>>("v1/inspectors");
+    assert!(response.is_err());
+}
+
+/// Check that operators can see pending mail items and expire stale preparations.
+#[test]
+fn test_expire_preparations() {
+    let (mut testkit, api, mock_provider) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 1);
+    testkit.create_block();
+
+    let tx = MailPreparation::new("", tx_alice.pub_key(), Amount(30), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let health: ServiceHealth = api.inner
+        .private(ApiKind::Service("cryptocurrency"))
+        .get("v1/health")
+        .unwrap();
+    assert_eq!(health.wallets, 2);
+    assert_eq!(health.pending_mail_items.count, 1);
+    assert_eq!(health.pending_mail_items.stale, 0);
+    assert_eq!(health.pending_mail_items.freezed_balance, 30);
+
+    let roles: RolesDump = api.inner
+        .private(ApiKind::Service("cryptocurrency"))
+        .get("v1/roles")
+        .unwrap();
+    assert_eq!(roles.inspectors, vec![*tx_bob.pub_key()]);
+
+    // Preparations that are not stale are not expired.
+    let (validator_key, validator_secret) = validator_keypair(&testkit);
+    let tx = ExpirePreparation::new(&validator_key, tx_alice.pub_key(), 0, &validator_secret);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 27, "description": "Preparation is not expired" }),
+    );
+
+    mock_provider.set_time(Utc::now() + Duration::seconds(PREPARATION_TTL + 60));
+    testkit.create_block();
+    testkit.create_block();
+
+    let responses: Vec<TransactionResponse> = api.inner
+        .private(ApiKind::Service("cryptocurrency"))
+        .query(&ExpirePreparationsQuery::default())
+        .post("v1/maintenance/expire-preparations")
+        .unwrap();
+    assert_eq!(responses.len(), 1);
+    testkit.create_block();
+    api.assert_tx_status(responses[0].tx_hash, &json!({ "type": "success" }));
+
+    let wallet = api.get_wallet(*tx_alice.pub_key()).unwrap();
+    assert_eq!(wallet.balance(), Amount(100));
+    assert_eq!(wallet.freezed_balance(), Amount(0));
+}
+
+/// Check that wallet events are returned starting from the requested height.
+#[test]
+fn test_wallet_events() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    testkit.create_block();
+
+    let tx = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(10), 0, &key_bob);
+    api.transfer(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let transfer_height = testkit.height().0;
+
+    let events = api.get_wallet_events(*tx_alice.pub_key(), 0, None);
+    assert_eq!(events.events.len(), 2);
+    assert_eq!(events.events[0].tx_hash, tx_alice.hash());
+    assert_eq!(events.next_height, transfer_height + 1);
+
+    let events = api.get_wallet_events(*tx_alice.pub_key(), transfer_height, None);
+    assert_eq!(events.events.len(), 1);
+    assert_eq!(events.events[0].tx_hash, tx.hash());
+    assert_eq!(events.events[0].height, Height(transfer_height));
+
+    // Nothing new has been committed, so the request waits and returns no events.
+    let events = api.get_wallet_events(*tx_alice.pub_key(), transfer_height + 1, Some(10));
+    assert!(events.events.is_empty());
+    assert_eq!(events.next_height, transfer_height + 1);
+
+    // A cancellation is streamed at its own height.
+    let tx_cancel =
+        Cancellation::new(tx_john.pub_key(), tx_bob.pub_key(), &tx.hash(), &key_john);
+    api.cancellation(&tx_cancel);
+    testkit.create_block();
+    api.assert_tx_status(tx_cancel.hash(), &json!({ "type": "success" }));
+    let cancel_height = testkit.height().0;
+
+    let events = api.get_wallet_events(*tx_alice.pub_key(), transfer_height + 1, Some(10));
+    assert_eq!(events.events.len(), 1);
+    assert_eq!(events.events[0].tx_hash, tx_cancel.hash());
+    assert_eq!(events.events[0].height, Height(cancel_height));
+    assert_eq!(events.next_height, cancel_height + 1);
+}
+
+/// Check that the number of requests waiting for new blocks is limited.
+#[test]
+fn test_block_notifier_waiters_limit() {
+    let notifier = Arc::new(BlockNotifier::default());
+    let waiters = (0..MAX_WAITERS)
+        .map(|_| {
+            let notifier = Arc::clone(&notifier);
+            thread::spawn(move || notifier.wait_above(Height(0), StdDuration::from_secs(30)))
+        })
+        .collect::<Vec<_>>();
+    while notifier.waiters() < MAX_WAITERS {
+        thread::yield_now();
+    }
+
+    // The limit is reached, so the request returns without waiting.
+    let start = Instant::now();
+    notifier.wait_above(Height(0), StdDuration::from_secs(30));
+    assert!(start.elapsed() < StdDuration::from_secs(10));
+
+    notifier.notify(Height(1));
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+    assert_eq!(notifier.waiters(), 0);
+}
+
+/// Check that transactions append structured events to the event log.
+#[test]
+fn test_event_log() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 1);
+    testkit.create_block();
+
+    let tx = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(10), 0, &key_bob);
+    api.transfer(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let transfer_height = testkit.height().0;
+
+    // Events of the first block are returned together even though only one was requested.
+    let log = api.get_events(0, Some(1));
+    assert_eq!(log.events.len(), 3);
+    assert!(log.proof.is_some());
+    assert_eq!(log.next_height, Some(transfer_height));
+    assert!(log.events.iter().any(|record| record.event() == PostEvent::RoleAssigned {
+        pub_key: *tx_bob.pub_key(),
+        role: 1,
+    }));
+
+    let log = api.get_events(transfer_height, None);
+    assert_eq!(log.events.len(), 1);
+    assert_eq!(log.events[0].tx_hash(), &tx.hash());
+    assert_eq!(log.events[0].height(), transfer_height);
+    assert_eq!(
+        log.events[0].event(),
+        PostEvent::Transferred {
+            from: *tx_bob.pub_key(),
+            to: *tx_alice.pub_key(),
+            asset: Hash::zero(),
+            amount: Amount(10),
+        }
+    );
+    assert_eq!(log.next_height, None);
+
+    let log = api.get_events(transfer_height + 1, None);
+    assert!(log.events.is_empty());
+    assert!(log.proof.is_none());
+}
+
+/// Check that operators can export per-wallet statements for a period.
+#[test]
+fn test_export() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let tx = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(10), 0, &key_bob);
+    api.transfer(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    // The endpoint serves the rendered document as is, so it is checked by rendering
+    // the statements directly.
+    let (from, to) = (Utc::now() - Duration::days(1), Utc::now() + Duration::days(1));
+    let snapshot = testkit.snapshot();
+    let schema = CurrencySchema::new(&snapshot);
+    let jsonl = export::render(&export::statements(&schema, from, to), ExportFormat::JsonLines);
+    assert_eq!(ExportFormat::JsonLines.content_type(), "application/x-ndjson");
+    let statements = jsonl
+        .lines()
+        .map(|line| serde_json::from_str::<Statement>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(statements.len(), 2);
+    let bob = statements
+        .iter()
+        .find(|statement| statement.pub_key == *tx_bob.pub_key())
+        .unwrap();
+    assert_eq!(bob.opening_balance, 0);
+    assert_eq!(bob.closing_balance, 90);
+    assert_eq!(bob.movements.len(), 2);
+    assert_eq!(bob.movements[1].tx_hash, tx.hash());
+    assert_eq!(bob.movements[1].amount, -10);
+
+    // Nothing happened in the period after the transfer.
+    let statements = export::statements(&schema, to, to + Duration::days(1));
+    let csv = export::render(&statements, ExportFormat::Csv);
+    assert_eq!(ExportFormat::Csv.content_type(), "text/csv; charset=utf-8");
+    let rows = csv.lines().collect::<Vec<_>>();
+    assert_eq!(rows[0], "wallet,name,entry,time,tx_hash,amount,balance,freezed_balance");
+    assert_eq!(rows.len(), 5);
+    assert!(rows.iter().all(|row| !row.contains(",movement,")));
+}
+
+/// Check that a wallet statement shows running balances of each history entry.
+#[test]
+fn test_wallet_statement() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 1);
+    testkit.create_block();
+
+    let tx_prepare = MailPreparation::new("", tx_alice.pub_key(), Amount(30), 0, &key_alice);
+    api.preparation(&tx_prepare);
+    testkit.create_block();
+    let tx_accept =
+        MailAcceptance::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(30), true, 0, &key_bob);
+    api.acceptance(&tx_accept);
+    testkit.create_block();
+    api.assert_tx_status(tx_accept.hash(), &json!({ "type": "success" }));
+
+    let query = StatementQuery {
+        pub_key: *tx_alice.pub_key(),
+        from: Utc::now() - Duration::days(1),
+        to: Utc::now() + Duration::days(1),
+    };
+    let statement: AccountStatement = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&query)
+        .get("v1/wallets/statement")
+        .unwrap();
+    assert_eq!(statement.opening_balance, 0);
+    assert_eq!(statement.closing_balance, 70);
+
+    let entries = statement
+        .entries
+        .iter()
+        .map(|entry| {
+            (
+                entry.movement.tx_hash,
+                entry.movement.amount,
+                entry.movement.balance,
+                entry.movement.freezed_balance,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        vec![
+            (tx_alice.hash(), 100, 100, 0),
+            (tx_prepare.hash(), -30, 70, 30),
+            (tx_accept.hash(), 0, 70, 0),
+        ]
+    );
+    assert!(statement.entries.iter().all(|entry| entry.transaction.is_some()));
+}
+
+/// Check that a cancellation appears in a statement as its own movement after the
+/// cancelled transaction.
+#[test]
+fn test_wallet_statement_with_cancellation() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    testkit.create_block();
+
+    let tx = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(10), 0, &key_bob);
+    api.transfer(&tx);
+    testkit.create_block();
+    let tx_cancel =
+        Cancellation::new(tx_john.pub_key(), tx_bob.pub_key(), &tx.hash(), &key_john);
+    api.cancellation(&tx_cancel);
+    testkit.create_block();
+    api.assert_tx_status(tx_cancel.hash(), &json!({ "type": "success" }));
+
+    let query = StatementQuery {
+        pub_key: *tx_alice.pub_key(),
+        from: Utc::now() - Duration::days(1),
+        to: Utc::now() + Duration::days(1),
+    };
+    let statement: AccountStatement = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&query)
+        .get("v1/wallets/statement")
+        .unwrap();
+    assert_eq!(statement.opening_balance, 0);
+    assert_eq!(statement.closing_balance, 100);
+    let entries = statement
+        .entries
+        .iter()
+        .map(|entry| (entry.movement.tx_hash, entry.movement.amount, entry.movement.balance))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        vec![
+            (tx_alice.hash(), 100, 100),
+            (tx.hash(), 10, 110),
+            (tx_cancel.hash(), -10, 100),
+        ]
+    );
+}
+
+/// Check that statements of wallets created before balances were recorded start
+/// from the balance recorded on their next change.
+#[test]
+fn test_wallet_statement_without_balances() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+    let tx = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(10), 0, &key_bob);
+    api.transfer(&tx);
+    testkit.create_block();
+
+    // Emulate a wallet created before balances were recorded.
+    {
+        let blockchain = testkit.blockchain_mut();
+        let mut fork = blockchain.fork();
+        {
+            let mut schema = CurrencySchema::new(&mut fork);
+            for index in 0..2 {
+                let key = balance_record_key(tx_bob.pub_key(), index);
+                schema.balance_records_mut().remove(&key);
+            }
+        }
+        blockchain.merge(fork.into_patch()).unwrap();
+    }
+
+    let tx = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(5), 1, &key_bob);
+    api.transfer(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let query = StatementQuery {
+        pub_key: *tx_bob.pub_key(),
+        from: Utc::now() - Duration::days(1),
+        to: Utc::now() + Duration::days(1),
+    };
+    let statement: AccountStatement = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&query)
+        .get("v1/wallets/statement")
+        .unwrap();
+    assert_eq!(statement.opening_balance, 90);
+    assert_eq!(statement.closing_balance, 85);
+    assert_eq!(statement.entries.len(), 1);
+    assert_eq!(statement.entries[0].movement.tx_hash, tx.hash());
+    assert_eq!(statement.entries[0].movement.amount, -5);
+}
+
+/// Check that the balance of a wallet can be queried as of a past block.
+#[test]
+fn test_balance_at() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    testkit.create_block();
+    let create_height = testkit.height().0;
+
+    let tx = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(10), 0, &key_bob);
+    api.transfer(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let transfer_height = testkit.height().0;
+
+    let balance = api.get_balance_at(*tx_alice.pub_key(), create_height - 1);
+    assert_eq!(balance.history_len, 0);
+    assert!(balance.balance.is_none());
+
+    let balance = api.get_balance_at(*tx_alice.pub_key(), create_height);
+    assert_eq!(balance.history_len, 1);
+    assert_eq!(balance.balance.unwrap().balance(), Amount(100));
+    assert_eq!(balance.block_proof.block.height(), Height(create_height));
+
+    let balance = api.get_balance_at(*tx_alice.pub_key(), transfer_height);
+    assert_eq!(balance.history_len, 2);
+    assert_eq!(balance.balance.clone().unwrap().balance(), Amount(110));
+    assert!(balance.history_proof.is_some());
+
+    // The balance is proven against the state hash of the latest block.
+    let proof = balance.balance_proof.unwrap();
+    let to_balance = proof.to_balance.check().unwrap();
+    let key = balance_record_key(tx_alice.pub_key(), 1);
+    assert_eq!(to_balance.entries(), vec![(&key, &balance.balance.unwrap())]);
+    let to_table = proof.to_table.check().unwrap();
+    assert_eq!(to_table.entries()[0].1, &to_balance.merkle_root());
+    assert_eq!(
+        to_table.merkle_root(),
+        *balance.latest_block_proof.block.state_hash()
+    );
+
+    // A later cancellation is counted only from its own height.
+    let tx_cancel =
+        Cancellation::new(tx_john.pub_key(), tx_bob.pub_key(), &tx.hash(), &key_john);
+    api.cancellation(&tx_cancel);
+    testkit.create_block();
+    api.assert_tx_status(tx_cancel.hash(), &json!({ "type": "success" }));
+    let cancel_height = testkit.height().0;
+
+    let balance = api.get_balance_at(*tx_alice.pub_key(), transfer_height);
+    assert_eq!(balance.history_len, 2);
+    assert_eq!(balance.balance.unwrap().balance(), Amount(110));
+    let balance = api.get_balance_at(*tx_alice.pub_key(), cancel_height);
+    assert_eq!(balance.history_len, 3);
+    assert_eq!(balance.balance.unwrap().balance(), Amount(100));
+}
+
+/// Check that timestamped transactions can be found by time.
+#[test]
+fn test_transactions_by_time() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+
+    let tx = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(10), 0, &key_bob);
+    api.transfer(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let mut query = TimeRangeQuery {
+        from: Utc::now() - Duration::hours(1),
+        to: Utc::now() + Duration::hours(1),
+        office: None,
+    };
+    let entries = api.get_entries_by_time(&query);
+    let hashes = entries.iter().map(|entry| *entry.tx_hash()).collect::<Vec<_>>();
+    // Transactions of one block are ordered by the block, not by the creation order.
+    assert_eq!(hashes.len(), 3);
+    assert!(hashes[..2].contains(&tx_alice.hash()));
+    assert!(hashes[..2].contains(&tx_bob.hash()));
+    assert_eq!(hashes[2], tx.hash());
+    assert_eq!(entries[2].height(), testkit.height().0);
+
+    query.from = query.to;
+    query.to = query.from + Duration::hours(1);
+    assert!(api.get_entries_by_time(&query).is_empty());
+
+    // Times before the Unix epoch belong to its first hour.
+    assert_eq!(hour_bucket(Utc.timestamp(-7200, 0)), 0);
+    query.from = Utc.timestamp(-7200, 0);
+    query.to = Utc.timestamp(0, 0);
+    assert!(api.get_entries_by_time(&query).is_empty());
+}
+
+/// Check that entries can be found by the post office and that entries timestamped
+/// before the time index was introduced are added to it by `IndexTimestamps`.
+#[test]
+fn test_time_index_by_office() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 1);
+    testkit.create_block();
+
+    let tx_prepare = MailPreparation::new("", tx_alice.pub_key(), Amount(30), 0, &key_alice);
+    api.preparation(&tx_prepare);
+    testkit.create_block();
+    let tx_accept =
+        MailAcceptance::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(30), true, 0, &key_bob);
+    api.acceptance(&tx_accept);
+    testkit.create_block();
+    api.assert_tx_status(tx_accept.hash(), &json!({ "type": "success" }));
+
+    let mut query = TimeRangeQuery {
+        from: Utc::now() - Duration::hours(1),
+        to: Utc::now() + Duration::hours(1),
+        office: Some(*tx_bob.pub_key()),
+    };
+    let entries = api.get_entries_by_time(&query);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(*entries[0].tx_hash(), tx_accept.hash());
+
+    // Emulate entries timestamped before the time index was introduced.
+    query.office = None;
+    let entries = api.get_entries_by_time(&query);
+    assert!(entries.len() > 1);
+    {
+        let start = testkit.height().next().0;
+        let blockchain = testkit.blockchain_mut();
+        let mut fork = blockchain.fork();
+        {
+            let mut schema = CurrencySchema::new(&mut fork);
+            for entry in &entries {
+                let hour = hour_bucket(entry.time());
+                schema.time_index_mut(hour).clear();
+                schema.office_time_index_mut(tx_bob.pub_key(), hour).clear();
+            }
+            schema.time_index_start_mut().set(start);
+        }
+        blockchain.merge(fork.into_patch()).unwrap();
+    }
+    assert!(api.get_entries_by_time(&query).is_empty());
+
+    let response: TransactionResponse = api.inner
+        .private(ApiKind::Service("cryptocurrency"))
+        .query(&IndexTimestampsQuery::default())
+        .post("v1/maintenance/index-timestamps")
+        .unwrap();
+    testkit.create_block();
+    api.assert_tx_status(response.tx_hash, &json!({ "type": "success" }));
+
+    let mut hashes = entries.iter().map(|entry| *entry.tx_hash()).collect::<Vec<_>>();
+    let mut backfilled = api
+        .get_entries_by_time(&query)
+        .iter()
+        .map(|entry| *entry.tx_hash())
+        .collect::<Vec<_>>();
+    hashes.sort();
+    backfilled.sort();
+    assert_eq!(backfilled, hashes);
+    query.office = Some(*tx_bob.pub_key());
+    assert_eq!(api.get_entries_by_time(&query).len(), 1);
+}
+
+/// Check that the error catalogue lists stable codes in order.
+#[test]
+fn test_error_catalogue() {
+    let (_, api, _) = create_testkit();
+    let catalogue: Vec<ErrorDescription> = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .get("v1/errors")
+        .unwrap();
+    for (code, error) in catalogue.iter().enumerate() {
+        assert_eq!(error.code as usize, code);
+    }
+    assert_eq!(catalogue[3].name, "InsufficientCurrencyAmount");
+    assert_eq!(catalogue[3].description, "Insufficient currency amount");
+    assert_eq!(catalogue[29].name, "TransactionNotFound");
+}
+
+/// Check that cancelling an unknown transaction fails with an error instead of a panic.
+#[test]
+fn test_cancellation_unknown_transaction() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    testkit.create_block();
+
+    let tx = Cancellation::new(
+        tx_john.pub_key(),
+        tx_john.pub_key(),
+        &crypto::hash(b"unknown"),
+        &key_john,
+    );
+    api.cancellation(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 29, "description": "Transaction doesn't exist" }),
+    );
+}
+
+/// Check that time dependent transactions are rejected before the time oracle reports.
+#[test]
+fn test_time_not_available() {
+    let (mut testkit, api) = create_testkit_without_time(ServiceConfig::default());
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    testkit.create_block_with_tx_hashes(&[tx_alice.hash()]);
+    assert_eq!(TimeSchema::new(testkit.snapshot()).time().get(), None);
+    api.assert_tx_status(
+        tx_alice.hash(),
+        &json!({ "type": "error", "code": 28, "description": "Time is not available" }),
+    );
+    api.assert_no_wallet(*tx_alice.pub_key());
+
+    // Transactions are accepted once the time oracle reports.
+    testkit.create_block();
+    assert!(TimeSchema::new(testkit.snapshot()).time().get().is_some());
+    let (tx_bob, _) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block();
+    api.assert_tx_status(tx_bob.hash(), &json!({ "type": "success" }));
+}
+
+/// Check that the block height policy lets the service work before the time oracle reports.
+#[test]
+fn test_time_fallback_to_block_height() {
+    let config = ServiceConfig {
+        time_policy: TimePolicy::BlockHeight,
+    };
+    let (mut testkit, api) = create_testkit_without_time(config);
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 0);
+    testkit.create_block_with_tx_hashes(&[tx_alice.hash(), tx_bob.hash()]);
+    api.assert_tx_status(tx_alice.hash(), &json!({ "type": "success" }));
+    api.assert_tx_status(tx_bob.hash(), &json!({ "type": "success" }));
+
+    let tx = Transfer::new(tx_bob.pub_key(), tx_alice.pub_key(), Amount(10), 0, &key_bob);
+    api.transfer(&tx);
+    testkit.create_block_with_tx_hashes(&[tx.hash()]);
+    assert_eq!(TimeSchema::new(testkit.snapshot()).time().get(), None);
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().balance(), Amount(110));
+
+    // Timestamps follow the block heights.
+    let statement: AccountStatement = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&StatementQuery {
+            pub_key: *tx_alice.pub_key(),
+            from: Utc.timestamp(0, 0),
+            to: Utc.timestamp(1_000, 0),
+        })
+        .get("v1/wallets/statement")
+        .unwrap();
+    let times = statement
+        .entries
+        .iter()
+        .map(|entry| entry.movement.time.timestamp())
+        .collect::<Vec<_>>();
+    assert_eq!(times, vec![testkit.height().0 as i64 - 1, testkit.height().0 as i64]);
+}
+
+/// Check that registered assets are issued, transferred and spent on mail separately
+/// from postage units.
+#[test]
+fn test_assets() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 2);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    let (tx_carol, key_carol) = api.create_wallet("Carol", 2);
+    testkit.create_block();
+
+    let tx_asset = CreateAsset::new(tx_bob.pub_key(), "Voucher", 0, &key_bob);
+    api.send(&tx_asset);
+    testkit.create_block();
+    api.assert_tx_status(tx_asset.hash(), &json!({ "type": "success" }));
+    let asset = tx_asset.hash();
+
+    let tx = IssueAsset::new(tx_alice.pub_key(), tx_bob.pub_key(), &asset, Amount(5), 0, &key_bob);
+    api.send(&tx);
+    // Only the issuer of the asset may issue it.
+    let tx_foreign =
+        IssueAsset::new(tx_alice.pub_key(), tx_carol.pub_key(), &asset, Amount(5), 0, &key_carol);
+    api.send(&tx_foreign);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    api.assert_tx_status(
+        tx_foreign.hash(),
+        &json!({
+            "type": "error",
+            "code": 33,
+            "description": "Pubkey doesn`t belong to asset issuer"
+        }),
+    );
+
+    let tx =
+        TransferAsset::new(tx_alice.pub_key(), tx_john.pub_key(), &asset, Amount(2), 0, &key_alice);
+    api.send(&tx);
+    let unknown = crypto::hash(b"unknown");
+    let tx_unknown = TransferAsset::new(
+        tx_alice.pub_key(),
+        tx_john.pub_key(),
+        &unknown,
+        Amount(1),
+        0,
+        &key_alice,
+    );
+    api.send(&tx_unknown);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    api.assert_tx_status(
+        tx_unknown.hash(),
+        &json!({ "type": "error", "code": 32, "description": "Asset doesn't exist" }),
+    );
+
+    let tx_prepare =
+        AssetMailPreparation::new("", tx_alice.pub_key(), &asset, Amount(3), 0, &key_alice);
+    api.send(&tx_prepare);
+    testkit.create_block();
+    api.assert_tx_status(tx_prepare.hash(), &json!({ "type": "success" }));
+    let assets = api.get_wallet_assets(*tx_alice.pub_key());
+    assert!(assets.balances.is_empty());
+
+    let tx_accept =
+        MailAcceptance::new(tx_john.pub_key(), tx_alice.pub_key(), Amount(3), true, 0, &key_john);
+    api.acceptance(&tx_accept);
+    testkit.create_block();
+    api.assert_tx_status(tx_accept.hash(), &json!({ "type": "success" }));
+
+    // Postage units are not affected.
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().balance(), Amount(100));
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().freezed_balance(), Amount(0));
+
+    let assets = api.get_wallet_assets(*tx_john.pub_key());
+    assert_eq!(assets.balances.len(), 1);
+    assert_eq!(*assets.balances[0].asset(), asset);
+    assert_eq!(assets.balances[0].balance(), Amount(2));
+    let proof = assets.proof.check().unwrap();
+    assert_eq!(proof.entries().len(), 1);
+
+    let registered: Vec<Asset> = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .get("v1/assets")
+        .unwrap();
+    assert_eq!(registered.len(), 1);
+    assert_eq!(registered[0].name(), "Voucher");
+    assert_eq!(registered[0].issuer(), tx_bob.pub_key());
+}
+
+/// Check that registered assets are not subject to spending and issue limits, that their
+/// moves are recorded in the wallet histories and can be cancelled by an inspector.
+#[test]
+fn test_asset_transfer_limits_and_cancellation() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 2);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    let (tx_carol, key_carol) = api.create_wallet("Carol", 0);
+    testkit.create_block();
+    assign_role(&mut testkit, &api, tx_carol.pub_key(), 3);
+
+    let tx_asset = CreateAsset::new(tx_bob.pub_key(), "Parcel credit", 0, &key_bob);
+    api.send(&tx_asset);
+    let tx = SetWalletLimits::new(tx_carol.pub_key(), tx_bob.pub_key(), 0, 0, 1, 0, &key_carol);
+    api.send(&tx);
+    testkit.create_block();
+    let asset = tx_asset.hash();
+    let tx = IssueAsset::new(tx_alice.pub_key(), tx_bob.pub_key(), &asset, Amount(5), 0, &key_bob);
+    api.send(&tx);
+    let tx = SetWalletLimits::new(tx_carol.pub_key(), tx_alice.pub_key(), 3, 0, 0, 0, &key_carol);
+    api.send(&tx);
+    testkit.create_block();
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().history_len(), 2);
+    let assets = api.get_wallet_assets(*tx_alice.pub_key());
+    assert_eq!(assets.balances[0].balance(), Amount(5));
+
+    let tx_transfer = TransferAsset::new(
+        tx_alice.pub_key(),
+        tx_carol.pub_key(),
+        &asset,
+        Amount(4),
+        0,
+        &key_alice,
+    );
+    api.send(&tx_transfer);
+    testkit.create_block();
+    api.assert_tx_status(tx_transfer.hash(), &json!({ "type": "success" }));
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().history_len(), 3);
+    assert_eq!(api.get_wallet(*tx_carol.pub_key()).unwrap().history_len(), 2);
+    assert_eq!(api.get_wallet_assets(*tx_carol.pub_key()).balances.len(), 1);
+
+    let tx = Cancellation::new(
+        tx_john.pub_key(),
+        tx_carol.pub_key(),
+        &tx_transfer.hash(),
+        &key_john,
+    );
+    api.cancellation(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let assets = api.get_wallet_assets(*tx_alice.pub_key());
+    assert_eq!(assets.balances[0].balance(), Amount(5));
+    assert!(api.get_wallet_assets(*tx_carol.pub_key()).balances.is_empty());
+    assert_eq!(api.get_wallet(*tx_carol.pub_key()).unwrap().history_len(), 3);
+}
+
+/// Check that a transaction is cancelled at most once and that only pending mail
+/// preparations can be cancelled.
+#[test]
+fn test_preparation_cancellation() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    testkit.create_block();
+
+    let tx_prepare = MailPreparation::new("", tx_alice.pub_key(), Amount(30), 0, &key_alice);
+    api.preparation(&tx_prepare);
+    testkit.create_block();
+    let tx =
+        Cancellation::new(tx_john.pub_key(), tx_alice.pub_key(), &tx_prepare.hash(), &key_john);
+    api.cancellation(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().balance(), Amount(100));
+
+    let tx = Cancellation::new(tx_john.pub_key(), tx_john.pub_key(), &tx_prepare.hash(), &key_john);
+    api.cancellation(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 46, "description": "Transaction is already cancelled" }),
+    );
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().balance(), Amount(100));
+
+    // An accepted mail item keeps its status.
+    let tx_prepare = MailPreparation::new("", tx_alice.pub_key(), Amount(30), 1, &key_alice);
+    api.preparation(&tx_prepare);
+    testkit.create_block();
+    let tx_accept =
+        MailAcceptance::new(tx_john.pub_key(), tx_alice.pub_key(), Amount(30), true, 0, &key_john);
+    api.acceptance(&tx_accept);
+    testkit.create_block();
+    let tx =
+        Cancellation::new(tx_john.pub_key(), tx_alice.pub_key(), &tx_prepare.hash(), &key_john);
+    api.cancellation(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 47, "description": "Mail item is not pending" }),
+    );
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().balance(), Amount(70));
+    let snapshot = testkit.snapshot();
+    let status = CurrencySchema::new(&snapshot).mail_statuses().get(&tx_prepare.hash());
+    assert_eq!(status, Some(MAIL_ACCEPTED));
+}
+
+/// Check that cancelling an acceptance returns the postage of the accepted preparation,
+/// and that acceptances of mail paid with registered assets can't be cancelled.
+#[test]
+fn test_acceptance_cancellation() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 2);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    testkit.create_block();
+
+    let tx_prepare = MailPreparation::new("", tx_alice.pub_key(), Amount(30), 0, &key_alice);
+    api.preparation(&tx_prepare);
+    testkit.create_block();
+    // The amount of the acceptance doesn't affect the frozen postage.
+    let tx_accept =
+        MailAcceptance::new(tx_john.pub_key(), tx_alice.pub_key(), Amount(50), true, 0, &key_john);
+    api.acceptance(&tx_accept);
+    testkit.create_block();
+    api.assert_tx_status(tx_accept.hash(), &json!({ "type": "success" }));
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().balance(), Amount(70));
+
+    let tx = Cancellation::new(tx_john.pub_key(), tx_alice.pub_key(), &tx_accept.hash(), &key_john);
+    api.cancellation(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().balance(), Amount(100));
+
+    let tx_asset = CreateAsset::new(tx_bob.pub_key(), "Parcel credit", 0, &key_bob);
+    api.send(&tx_asset);
+    testkit.create_block();
+    let asset = tx_asset.hash();
+    let tx = IssueAsset::new(tx_alice.pub_key(), tx_bob.pub_key(), &asset, Amount(5), 0, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+    let tx_prepare =
+        AssetMailPreparation::new("", tx_alice.pub_key(), &asset, Amount(3), 1, &key_alice);
+    api.send(&tx_prepare);
+    testkit.create_block();
+    let tx_accept =
+        MailAcceptance::new(tx_john.pub_key(), tx_alice.pub_key(), Amount(3), true, 1, &key_john);
+    api.acceptance(&tx_accept);
+    testkit.create_block();
+    api.assert_tx_status(tx_accept.hash(), &json!({ "type": "success" }));
+
+    let tx = Cancellation::new(tx_john.pub_key(), tx_alice.pub_key(), &tx_accept.hash(), &key_john);
+    api.cancellation(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 45, "description": "Acceptance can't be cancelled" }),
+    );
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().balance(), Amount(100));
+    assert_eq!(api.get_wallet_assets(*tx_alice.pub_key()).balances[0].balance(), Amount(2));
+}
+
+/// Check that vouchers are spent only on mail and become void after their expiry.
+#[test]
+fn test_vouchers() {
+    let (mut testkit, api, mock_provider) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 2);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    testkit.create_block();
+
+    let expires_at = Utc::now() + Duration::days(1);
+    let tx_voucher = CreateVoucher::new(tx_bob.pub_key(), "Free letter", expires_at, 0, &key_bob);
+    api.send(&tx_voucher);
+    testkit.create_block();
+    api.assert_tx_status(tx_voucher.hash(), &json!({ "type": "success" }));
+    let voucher = tx_voucher.hash();
+
+    let tx =
+        IssueAsset::new(tx_alice.pub_key(), tx_bob.pub_key(), &voucher, Amount(2), 0, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let tx = TransferAsset::new(
+        tx_alice.pub_key(),
+        tx_john.pub_key(),
+        &voucher,
+        Amount(1),
+        0,
+        &key_alice,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 34, "description": "Voucher is not transferable" }),
+    );
+
+    let tx = AssetMailPreparation::new("", tx_alice.pub_key(), &voucher, Amount(1), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let tx =
+        MailAcceptance::new(tx_john.pub_key(), tx_alice.pub_key(), Amount(1), true, 0, &key_john);
+    api.acceptance(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let assets = api.get_wallet_assets(*tx_alice.pub_key());
+    assert_eq!(assets.balances[0].balance(), Amount(1));
+    assert_eq!(assets.vouchers.len(), 1);
+    assert_eq!(assets.vouchers[0].expires_at(), expires_at);
+
+    mock_provider.set_time(expires_at + Duration::minutes(1));
+    for _ in 0..3 {
+        testkit.create_block();
+    }
+    let tx = AssetMailPreparation::new("", tx_alice.pub_key(), &voucher, Amount(1), 1, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 35, "description": "Voucher is expired" }),
+    );
+}
+
+/// Check that vouchers cannot expire in the past and are voided instead of being swept
+/// when the wallet holding them is closed.
+#[test]
+fn test_vouchers_void_on_close() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 2);
+    let (tx_carol, _) = api.create_wallet("Carol", 0);
+    testkit.create_block();
+
+    let expired_at = Utc::now() - Duration::days(1);
+    let tx = CreateVoucher::new(tx_bob.pub_key(), "Old letter", expired_at, 0, &key_bob);
+    api.send(&tx);
+    let expires_at = Utc::now() + Duration::days(1);
+    let tx_voucher = CreateVoucher::new(tx_bob.pub_key(), "Free letter", expires_at, 1, &key_bob);
+    api.send(&tx_voucher);
+    let tx_asset = CreateAsset::new(tx_bob.pub_key(), "Parcel credit", 2, &key_bob);
+    api.send(&tx_asset);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 43, "description": "Time is in the past" }),
+    );
+    let (voucher, asset) = (tx_voucher.hash(), tx_asset.hash());
+
+    let tx =
+        IssueAsset::new(tx_alice.pub_key(), tx_bob.pub_key(), &voucher, Amount(2), 0, &key_bob);
+    api.send(&tx);
+    let tx = IssueAsset::new(tx_alice.pub_key(), tx_bob.pub_key(), &asset, Amount(3), 1, &key_bob);
+    api.send(&tx);
+    testkit.create_block();
+    assert_eq!(api.get_wallet_assets(*tx_alice.pub_key()).balances.len(), 2);
+
+    let tx = CloseWallet::new(tx_alice.pub_key(), tx_carol.pub_key(), 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let assets = api.get_wallet_assets(*tx_carol.pub_key());
+    assert_eq!(assets.balances.len(), 1);
+    assert_eq!(*assets.balances[0].asset(), asset);
+    assert_eq!(assets.balances[0].balance(), Amount(3));
+    assert!(assets.vouchers.is_empty());
+    assert!(api.get_wallet_assets(*tx_alice.pub_key()).balances.is_empty());
+}
+
+/// Check that numbered stamps are minted, transferred and consumed by mail items.
+#[test]
+fn test_stamps() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 2);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    let (tx_carol, key_carol) = api.create_wallet("Carol", 0);
+    let (tx_dave, key_dave) = api.create_wallet("Dave", 2);
+    testkit.create_block();
+
+    let artwork = crypto::hash(b"artwork");
+    let tx = MintStamps::new(
+        tx_bob.pub_key(),
+        tx_alice.pub_key(),
+        "2018-A",
+        1,
+        3,
+        Amount(50),
+        &artwork,
+        0,
+        &key_bob,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let tx = MintStamps::new(
+        tx_bob.pub_key(),
+        tx_alice.pub_key(),
+        "2018-A",
+        3,
+        2,
+        Amount(50),
+        &artwork,
+        1,
+        &key_bob,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 36, "description": "Stamp already exists" }),
+    );
+    // Another issuer's series with the same name is a different series.
+    let tx = MintStamps::new(
+        tx_dave.pub_key(),
+        tx_dave.pub_key(),
+        "2018-A",
+        1,
+        1,
+        Amount(50),
+        &artwork,
+        0,
+        &key_dave,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let foreign = stamp_id(tx_dave.pub_key(), "2018-A", 1);
+    assert_ne!(foreign, stamp_id(tx_bob.pub_key(), "2018-A", 1));
+
+    let first = stamp_id(tx_bob.pub_key(), "2018-A", 1);
+    let second = stamp_id(tx_bob.pub_key(), "2018-A", 2);
+    let tx = TransferStamp::new(tx_alice.pub_key(), tx_carol.pub_key(), &first, 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    // The stamp is consumed by the mail item and cannot be used again.
+    let tx = StampMailPreparation::new("", tx_alice.pub_key(), &second, 0, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let tx =
+        MailAcceptance::new(tx_john.pub_key(), tx_alice.pub_key(), Amount(0), true, 0, &key_john);
+    api.acceptance(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let tx = StampMailPreparation::new("", tx_alice.pub_key(), &second, 1, &key_alice);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 39, "description": "Stamp is already used" }),
+    );
+
+    // A rejected mail item returns the stamp.
+    let tx = StampMailPreparation::new("", tx_carol.pub_key(), &first, 0, &key_carol);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+    let tx =
+        MailAcceptance::new(tx_john.pub_key(), tx_carol.pub_key(), Amount(0), false, 1, &key_john);
+    api.acceptance(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let stamps: WalletStamps = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&WalletQuery { pub_key: *tx_carol.pub_key() })
+        .get("v1/wallets/stamps")
+        .unwrap();
+    assert_eq!(stamps.stamps.len(), 1);
+    assert_eq!(stamps.stamps[0].number(), 1);
+    assert_eq!(stamps.stamps[0].denomination(), Amount(50));
+    assert!(!stamps.stamps[0].is_used());
+    assert_eq!(stamps.proof.check().unwrap().entries().len(), 1);
+
+    let stamps: WalletStamps = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .query(&WalletQuery { pub_key: *tx_alice.pub_key() })
+        .get("v1/wallets/stamps")
+        .unwrap();
+    assert_eq!(stamps.stamps.len(), 2);
+    assert_eq!(stamps.stamps.iter().filter(|stamp| stamp.is_used()).count(), 1);
+    // Postage units are not affected.
+    assert_eq!(api.get_wallet(*tx_alice.pub_key()).unwrap().balance(), Amount(100));
+}
+
+/// Check that a sorting machine assigned by a validator redeems the stamp of an accepted
+/// mail item exactly once.
+#[test]
+fn test_stamp_redemption() {
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_bob, key_bob) = api.create_wallet(BOB_NAME, 2);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    let (tx_machine, key_machine) = api.create_wallet("Sorter", 0);
+    // The sorting machine role cannot be claimed when creating a wallet.
+    let (tx_rogue, key_rogue) = api.create_wallet("Rogue", 4);
+    testkit.create_block();
+    assign_role(&mut testkit, &api, tx_machine.pub_key(), 4);
+
+    let tx = MintStamps::new(
+        tx_bob.pub_key(),
+        tx_alice.pub_key(),
+        "2018-B",
+        1,
+        1,
+        Amount(50),
+        &crypto::hash(b"artwork"),
+        0,
+        &key_bob,
+    );
+    api.send(&tx);
+    testkit.create_block();
+    let stamp = stamp_id(tx_bob.pub_key(), "2018-B", 1);
+
+    let tx_prepare = StampMailPreparation::new("", tx_alice.pub_key(), &stamp, 0, &key_alice);
+    api.send(&tx_prepare);
+    testkit.create_block();
+    api.assert_tx_status(tx_prepare.hash(), &json!({ "type": "success" }));
+    let mail_item = tx_prepare.hash();
+
+    let verification = api.verify_stamp(StampQuery { stamp: None, mail_item: Some(mail_item) });
+    assert!(verification.valid && verification.unused && !verification.accepted);
+
+    let tx = RedeemStamp::new(tx_machine.pub_key(), &stamp, 0, &key_machine);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 41, "description": "Mail item is not accepted" }),
+    );
+
+    let tx =
+        MailAcceptance::new(tx_john.pub_key(), tx_alice.pub_key(), Amount(0), true, 0, &key_john);
+    api.acceptance(&tx);
+    testkit.create_block();
+    let verification = api.verify_stamp(StampQuery { stamp: Some(stamp), mail_item: None });
+    assert!(verification.valid && verification.unused && verification.accepted);
+    // The acceptance is recorded in the stamp and thus covered by the stamp proof.
+    let proof = verification.stamp_proof.check().unwrap();
+    assert_eq!(proof.entries()[0].1.acceptance(), &tx.hash());
+
+    let tx = RedeemStamp::new(tx_rogue.pub_key(), &stamp, 1, &key_rogue);
+    let tx_redeem = RedeemStamp::new(tx_machine.pub_key(), &stamp, 1, &key_machine);
+    api.send(&tx);
+    api.send(&tx_redeem);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({
+            "type": "error",
+            "code": 40,
+            "description": "Pubkey doesn`t belong to sorting machine"
+        }),
+    );
+    api.assert_tx_status(tx_redeem.hash(), &json!({ "type": "success" }));
+
+    let tx = RedeemStamp::new(tx_machine.pub_key(), &stamp, 2, &key_machine);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(
+        tx.hash(),
+        &json!({ "type": "error", "code": 42, "description": "Stamp is already redeemed" }),
+    );
+
+    let verification = api.verify_stamp(StampQuery { stamp: None, mail_item: Some(mail_item) });
+    assert!(verification.valid && !verification.unused && verification.accepted);
+    assert_eq!(verification.redemption.unwrap().machine(), tx_machine.pub_key());
+    let proof = verification.redemption_proof.check().unwrap();
+    assert_eq!(proof.entries().len(), 1);
+    let proof = verification.stamp_proof.check().unwrap();
+    assert_eq!(proof.entries().len(), 1);
+
+    let verification = api.verify_stamp(StampQuery {
+        stamp: Some(stamp_id(tx_bob.pub_key(), "2018-B", 2)),
+        mail_item: None,
+    });
+    assert!(!verification.valid && !verification.accepted);
+}
+
+/// Check that a postage token of a mail item is verified offline against the validators,
+/// including the signed preparation and its status.
+#[test]
+fn test_postage_token() {
+    // Test vectors of RFC 9285.
+    assert_eq!(base45_encode(b"AB"), "BB8");
+    assert_eq!(base45_encode(b"Hello!!"), "%69 VD92EX0");
+    assert_eq!(base45_decode("QED8WEX0").unwrap(), b"ietf!".to_vec());
+    assert_eq!(base45_decode("GGW"), Err(TokenError::InvalidEncoding));
+
+    let (mut testkit, api, _) = create_testkit();
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME, 0);
+    let (tx_john, key_john) = api.create_wallet(JOHN_NAME, 1);
+    testkit.create_block();
+    let tx = MailPreparation::new("", tx_alice.pub_key(), Amount(10), 0, &key_alice);
+    let tx_failed = MailPreparation::new("", tx_alice.pub_key(), Amount(1000), 1, &key_alice);
+    api.preparation(&tx);
+    api.preparation(&tx_failed);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+
+    let token = api.mail_token(tx.hash()).unwrap();
+    let validators: Vec<PublicKey> = api.inner
+        .public(ApiKind::Service("cryptocurrency"))
+        .get("v1/validators")
+        .unwrap();
+
+    let decoded = PostageToken::from_base45(&token.token).unwrap();
+    assert_eq!(decoded.mail_item, tx.hash());
+    assert_eq!(decoded.to_base45(), token.token);
+    assert_eq!(decoded.verify(&validators), Ok(MAIL_PENDING));
+    let preparation: MailPreparation = Message::from_raw(decoded.preparation.clone()).unwrap();
+    assert_eq!(preparation.pub_key(), tx_alice.pub_key());
+    assert_eq!(preparation.amount(), Amount(10));
+
+    let (other_key, _) = crypto::gen_keypair();
+    assert_eq!(decoded.verify(&[other_key]), Err(TokenError::InvalidPrecommit));
+    let mut forged = PostageToken::from_base45(&token.token).unwrap();
+    forged.mail_item = crypto::hash(b"forged");
+    assert_eq!(forged.verify(&validators), Err(TokenError::InvalidPreparation));
+    // Any committed transaction other than a mail preparation is refused.
+    let mut forged = PostageToken::from_base45(&token.token).unwrap();
+    forged.mail_item = tx_alice.hash();
+    forged.preparation = tx_alice.raw().clone();
+    assert_eq!(forged.verify(&validators), Err(TokenError::InvalidPreparation));
+    let bytes = decoded.to_bytes();
+    assert!(PostageToken::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+    // Tokens are issued only for successful mail preparations.
+    assert!(api.mail_token(tx_alice.hash()).is_none());
+    assert!(api.mail_token(tx_failed.hash()).is_none());
+
+    // A cancelled mail item is void in tokens issued afterwards.
+    let tx_cancel =
+        Cancellation::new(tx_john.pub_key(), tx_alice.pub_key(), &tx.hash(), &key_john);
+    api.cancellation(&tx_cancel);
+    testkit.create_block();
+    api.assert_tx_status(tx_cancel.hash(), &json!({ "type": "success" }));
+    let token = api.mail_token(tx.hash()).unwrap();
+    let decoded = PostageToken::from_base45(&token.token).unwrap();
+    assert_eq!(decoded.verify(&validators), Err(TokenError::MailItemVoid));
+}
+
+/// Wrapper for the cryptocurrency service API allowing to easily use it
+/// (compared to `TestKitApi` calls).
+struct CryptocurrencyApi {
+    pub inner: TestKitApi,
+}
+
+impl CryptocurrencyApi {
+    /// Generates a wallet creation transaction with a random key pair, sends it over HTTP,
+    /// and checks the synchronous result (i.e., the hash of the transaction returned
+    /// within the response).
+    /// Note that the transaction is not immediately added to the blockchain, but rather is put
+    /// to the pool of unconfirmed transactions.
+    fn create_wallet(&self, name: &str, user: u64) -> (CreateWallet, SecretKey) {
+        let (pubkey, key) = crypto::gen_keypair();
+        // Create a pre-signed transaction
+        let tx = CreateWallet::new(&pubkey, name, user, &key);
+        let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&tx)
+            .post("v1/wallets/transaction")
+            .unwrap();
+        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
+        (tx, key)
+    }
+
+    fn get_wallet_info(&self, pub_key: PublicKey) -> WalletInfo {
+        self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&WalletQuery { pub_key })
+            .get::<WalletInfo>("v1/wallets/info")
+            .unwrap()
+    }
+
+    fn get_wallet_events(
+        &self,
+        pub_key: PublicKey,
+        from_height: u64,
+        timeout: Option<u64>,
+    ) -> WalletEvents {
+        self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&EventsQuery { pub_key, from_height, timeout })
+            .get::<WalletEvents>("v1/wallets/events")
+            .unwrap()
+    }
+
+    fn get_entries_by_time(&self, query: &TimeRangeQuery) -> Vec<TimeIndexEntry> {
+        self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(query)
+            .get("v1/transactions/by-time")
+            .unwrap()
+    }
+
+    fn get_events(&self, from_height: u64, count: Option<usize>) -> EventLog {
+        self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&EventLogQuery { from_height, count })
+            .get::<EventLog>("v1/events")
+            .unwrap()
+    }
+
+    fn get_balance_at(&self, pub_key: PublicKey, height: u64) -> HistoricalBalance {
+        self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&BalanceAtQuery { pub_key, height })
+            .get::<HistoricalBalance>("v1/wallets/balance-at")
+            .unwrap()
+    }
+
+    fn get_wallet_assets(&self, pub_key: PublicKey) -> WalletAssets {
+        self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&WalletQuery { pub_key })
+            .get::<WalletAssets>("v1/wallets/assets")
+            .unwrap()
+    }
+
+    fn verify_stamp(&self, query: StampQuery) -> StampVerification {
+        self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&query)
+            .get::<StampVerification>("v1/stamps/verify")
+            .unwrap()
+    }
+
+    fn mail_token(&self, mail_item: Hash) -> Option<MailToken> {
+        self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&MailItemQuery { mail_item })
+            .get::<MailToken>("v1/mail/token")
+            .ok()
+    }
+
+    fn get_wallet(&self, pub_key: PublicKey) -> Option<Wallet> {
+        let wallet_info = self.get_wallet_info(pub_key);
+
+        let to_wallet = wallet_info.wallet_proof.to_wallet.check().unwrap();
+        to_wallet
+            .all_entries()
+            .iter()
+            .find(|(ref k, _)| **k == pub_key)
+            .and_then(|tuple| tuple.1)
+            .cloned()
+    }
+
+    fn preparation(&self, tx: &MailPreparation) {
+        let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&tx)
+            .post("v1/wallets/transaction")
+            .unwrap();
+        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
+    }
+
+    fn acceptance(&self, tx: &MailAcceptance) {
+        let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&tx)
+            .post("v1/wallets/transaction")
+            .unwrap();
+        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));        
+    }
+    
+    /// Sends a transfer transaction over HTTP and checks the synchronous result.
+    fn transfer(&self, tx: &Transfer) {
+        let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&tx)
+            .post("v1/wallets/transaction")
+            .unwrap();
+        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
+    }
+
+    fn issue(&self, tx: &Issue) {
+        let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&tx)
+            .post("v1/wallets/transaction")
+            .unwrap();
+        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
+    }
+
+    fn cancellation(&self, tx: &Cancellation) {
+    	let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&tx)
+            .post("v1/wallets/transaction")
+            .unwrap();
+
+        assert_eq!(tx_info, json!({ "tx_hash": tx.hash()}));
+    }
+
+    /// Sends an arbitrary service transaction over HTTP and checks the synchronous result.
+    fn send<T: Message + Serialize>(&self, tx: &T) {
+        let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(tx)
+            .post("v1/wallets/transaction")
+            .unwrap();
+        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
+    }
+
+    /// Asserts that a wallet with the specified public key is not known to the blockchain.
+    fn assert_no_wallet(&self, pub_key: PublicKey) {
+        let wallet_info: WalletInfo = self.inner
+            .public(ApiKind::Service("cryptocurrency"))
+            .query(&WalletQuery { pub_key })
+            .get("v1/wallets/info")
+            .unwrap();
+
+        let to_wallet = wallet_info.wallet_proof.to_wallet.check().unwrap();
+        assert!(
+            to_wallet
+                .missing_keys()
+                .iter()
+                .find(|v| ***v == pub_key)
+                .is_some()
+        )
+    }
+
+    /// Asserts that the transaction with the given hash has a specified status.
+    fn assert_tx_status(&self, tx_hash: Hash, expected_status: &serde_json::Value) {
+        let info: serde_json::Value = self.inner
+            .public(ApiKind::Explorer)
+            .query(&TransactionQuery::new(tx_hash))
+            .get("v1/transactions")
+            .unwrap();
+        if let serde_json::Value::Object(mut info) = info {
+            let tx_status = info.remove("status").unwrap();
+            assert_eq!(tx_status, *expected_status);
+        } else {
+            panic!("Invalid transaction info format, object expected");
+        }
+    }
+}
+
+/// Creates a testkit together with the API wrapper defined above.
+fn create_testkit() -> (TestKit, CryptocurrencyApi, MockTimeProvider) {
+	let mock_provider = MockTimeProvider::new(SystemTime::now().into());
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(CurrencyService::default())
+        .with_service(TimeService::with_provider(mock_provider.clone()))
+        .create();
+    let api = CryptocurrencyApi {
+        inner: testkit.api(),
+    };
+    testkit.create_blocks_until(Height(2)); 
+    (testkit, api, mock_provider)
+}
+
+/// Assigns the `role` to the wallet with a transaction signed by the validator.
+fn assign_role(testkit: &mut TestKit, api: &CryptocurrencyApi, pub_key: &PublicKey, role: u64) {
+    let (validator_key, validator_secret) = validator_keypair(testkit);
+    let tx = AssignRole::new(&validator_key, pub_key, role, 0, &validator_secret);
+    api.send(&tx);
+    testkit.create_block();
+    api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
+}
+
+/// Returns the service keypair of the first validator.
+fn validator_keypair(testkit: &TestKit) -> (PublicKey, SecretKey) {
+    let (pub_key, secret_key) = testkit.network().validators()[0].service_keypair();
+    (*pub_key, secret_key.clone())
+}
+
+/// Creates a testkit with the time oracle that has not reported yet. The time stays unknown
+/// while blocks are created from the given transactions only, leaving out those of the oracle.
+fn create_testkit_without_time(config: ServiceConfig) -> (TestKit, CryptocurrencyApi) {
+    let mock_provider = MockTimeProvider::new(SystemTime::now().into());
+    let testkit = TestKitBuilder::validator()
+        .with_service(CurrencyService::with_config(config))
+        .with_service(TimeService::with_provider(mock_provider))
+        .create();
+    let api = CryptocurrencyApi {
+        inner: testkit.api(),
+    };
+    (testkit, api)
 }